@@ -5,53 +5,271 @@
 #![no_std]
 
 use core::ffi::{c_double, c_void, CStr};
+use core::fmt;
 use core::mem;
-use core::sync::atomic::{AtomicU32, Ordering};
+use core::ops::RangeInclusive;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, AtomicUsize, Ordering};
+use flipperzero::furi::string::FuriString;
 use flipperzero::furi::sync::Mutex;
-use flipperzero::gpio::i2c;
 use flipperzero::{format, println};
 use flipperzero::furi::time::FuriDuration;
 use flipperzero_rt::{entry, manifest};
 
 use flipperzero_sys as sys;
 
+use embedded_graphics::{
+    pixelcolor::BinaryColor, prelude::*, primitives::{Line, PrimitiveStyle, Rectangle},
+};
+
 use shared::sprintf;
+use shared::fmt::display;
+use shared::furi::delay::Delay;
+use shared::furi::hal::i2c::I2cBus;
 use shared::furi::hal::power::Power;
+use shared::furi::hal::serial::{AsyncSerialReceiver, SerialEvent, SerialHandle};
 use shared::furi::record::Record;
-use shared::gui::{Gui, ViewDispatcher, ViewId, View};
-use shared::nicla_sense_env::{NiclaSenseEnv, IndoorSensorMode, OutdoorSensorMode};
+use shared::gui::{Canvas, Gui, ViewDispatcher, ViewId, View};
+use shared::storage::Storage;
+use shared::nicla_sense_env::{
+    self, CsvDecoder, CsvError, IndoorSensorMode, Measurement, NiclaError, NiclaSenseEnv, OrangeLed, OutdoorSensorMode,
+    Snapshot,
+};
 
 static SAMPLE_COUNT: AtomicU32 = AtomicU32::new(0);
-static VALUES: Mutex<Measurement> = Mutex::new(Measurement::new());
-
-struct Measurement {
-    temperature: f32,
-    humidity: f32,
-    epa_eqa: u16,
-    fast_eqa: u16,
-    o3: f32,
-    no2: f32,
-    eco2: f32,
-    tvoc: f32,
-    c2h6o: f32,
-    relative_iaq: f32,
+static BOARDS: Mutex<heapless::Vec<Board, MAX_BOARDS>> = Mutex::new(heapless::Vec::new());
+static POWER: Mutex<PowerStatus> = Mutex::new(PowerStatus::new());
+static SETTINGS: Mutex<Settings> = Mutex::new(Settings::new());
+static MODE_MENU: Mutex<ModeMenu> = Mutex::new(ModeMenu::new());
+/// Sensor modes of all boards.
+static MODES: Mutex<Modes> = Mutex::new(Modes::new());
+/// Cleaning cycle started from the sensor mode screen.
+static CLEANING: Mutex<Option<Cleaning>> = Mutex::new(None);
+
+/// Index of the board currently displayed.
+static PAGE: AtomicUsize = AtomicUsize::new(0);
+
+/// Show the ZMOD4410 rather than the ZMOD4510 on the diagnostics view.
+static DIAGNOSTICS_INDOOR: AtomicBool = AtomicBool::new(true);
+
+/// Read measurements from the UART CSV stream rather than I2C.
+static USE_UART: AtomicBool = AtomicBool::new(false);
+/// Index of the board connected to UART.
+static UART_BOARD: AtomicUsize = AtomicUsize::new(0);
+static CSV_DELIMITER: AtomicU8 = AtomicU8::new(DEFAULT_CSV_DELIMITER);
+static CSV_DECODER: Mutex<CsvDecoder> = Mutex::new(CsvDecoder::new(DEFAULT_CSV_DELIMITER));
+
+const MAIN_VIEW: ViewId = ViewId(0);
+const SETTINGS_VIEW: ViewId = ViewId(1);
+const MODES_VIEW: ViewId = ViewId(2);
+const DIAGNOSTICS_VIEW: ViewId = ViewId(3);
+const CLEANING_VIEW: ViewId = ViewId(4);
+
+/// Custom events, used for slow actions so that the view can show they are in progress.
+const EVENT_PERSIST_SETTINGS: u32 = 0;
+const EVENT_FACTORY_RESET: u32 = 1;
+
+/// Maximum number of boards on the bus.
+const MAX_BOARDS: usize = 4;
+
+/// Number of entries on the settings screen.
+const SETTINGS_ITEMS: usize = 5;
+/// Number of entries on the sensor mode screen.
+const MODE_ITEMS: usize = 3;
+
+/// Sensor modes in discriminant order, so that `mode as usize` is the index.
+const INDOOR_MODES: [(IndoorSensorMode, &str); 6] = [
+    (IndoorSensorMode::Off, "Off"),
+    (IndoorSensorMode::Cleaning, "Cleaning"),
+    (IndoorSensorMode::IndoorAirQuality, "IAQ"),
+    (IndoorSensorMode::IndoorAirQualityLowPower, "IAQ low power"),
+    (IndoorSensorMode::PublicBuildingAirQuality, "PBAQ"),
+    (IndoorSensorMode::Sulfur, "Sulfur odor"),
+];
+const OUTDOOR_MODES: [(OutdoorSensorMode, &str); 3] = [
+    (OutdoorSensorMode::Off, "Off"),
+    (OutdoorSensorMode::Cleaning, "Cleaning"),
+    (OutdoorSensorMode::OutdoorAirQuality, "OAQ"),
+];
+
+/// Range of the Rmox bar chart (log10 Ohm).
+const RMOX_LOG_RANGE: RangeInclusive<f32> = 2.0..=10.0;
+
+/// Restore the previous mode even if the end of cleaning was not detected.
+///
+/// The length of a cleaning cycle is not documented, so this is only a fallback for when the mode and ZMOD
+/// status registers never show the end of the cycle.
+const CLEANING_TIMEOUT_SECS: u32 = 3 * 60;
+/// ZMOD status bit set while the measurement sequencer is running.
+const ZMOD_STATUS_SEQUENCER_RUNNING: u8 = 0x80;
+
+const MODES_DIR: &CStr = c"/ext/apps_data/nicla_sense_env";
+const MODES_PATH: &CStr = c"/ext/apps_data/nicla_sense_env/modes.bin";
+
+// Nicla TX/RX are connected to pins 13/14
+const UART_CHANNEL: sys::FuriHalSerialId = sys::FuriHalSerialIdUsart;
+/// Baud rate used when the board's rate can't be read over I2C.
+const DEFAULT_UART_BAUD: u32 = 38400;
+const DEFAULT_CSV_DELIMITER: u8 = b',';
+
+/// App errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Error {
+    Nicla(NiclaError),
+    Csv(CsvError),
+    /// Serial interface is in use by another app.
+    UartBusy,
+    /// Another board already uses the address.
+    AddressInUse(u8),
+    /// Failed to write to the SD card.
+    Storage,
+}
+
+impl From<NiclaError> for Error {
+    fn from(err: NiclaError) -> Self {
+        Error::Nicla(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Nicla(err) => err.fmt(f),
+            Error::Csv(err) => write!(f, "CSV {err}"),
+            Error::UartBusy => f.write_str("UART busy"),
+            Error::AddressInUse(address) => write!(f, "0x{address:02X} in use"),
+            Error::Storage => f.write_str("failed to save"),
+        }
+    }
+}
+
+/// Settings screen state.
+struct Settings {
+    selected: usize,
+    /// Index into [`nicla_sense_env::BAUD_RATE_MAP`].
+    baud_index: usize,
+    /// New I2C address.
+    address: u8,
+    /// Factory reset was selected once and needs confirming.
+    confirm_reset: bool,
+    /// Result of the last action.
+    status: Option<Result<&'static str, Error>>,
+}
+
+impl Settings {
+    pub const fn new() -> Self {
+        Settings {
+            selected: 0,
+            baud_index: 0,
+            address: nicla_sense_env::DEFAULT_DEVICE_ADDRESS,
+            confirm_reset: false,
+            status: None,
+        }
+    }
+}
+
+/// Sensor mode screen state.
+struct ModeMenu {
+    selected: usize,
+    /// Index into [`INDOOR_MODES`].
+    indoor: usize,
+    /// Index into [`OUTDOOR_MODES`].
+    outdoor: usize,
+    /// Sensor to clean.
+    clean_indoor: bool,
+    /// Result of the last action.
+    status: Option<Result<&'static str, Error>>,
+}
+
+impl ModeMenu {
+    pub const fn new() -> Self {
+        ModeMenu {
+            selected: 0,
+            indoor: 0,
+            outdoor: 0,
+            clean_indoor: true,
+            status: None,
+        }
+    }
+}
+
+/// Guided MOx cleaning cycle.
+struct Cleaning {
+    /// Clean the ZMOD4410 rather than the ZMOD4510.
+    indoor: bool,
+    address: u8,
+    /// Modes of the board before cleaning, restored afterwards.
+    previous: Modes,
+    /// RTC timestamp at which cleaning started.
+    started: u32,
+    /// Last value of the ZMOD status register.
+    zmod_status: Option<u8>,
+    /// ZMOD sequencer was seen running since cleaning started.
+    sequencer_ran: bool,
+    /// Back was pressed once while cleaning.
+    warned: bool,
+    /// Outcome, `None` while cleaning.
+    result: Option<Result<&'static str, Error>>,
+}
+
+/// Sensor modes, saved to storage.
+#[derive(Debug, Clone, Copy)]
+struct Modes {
+    indoor: IndoorSensorMode,
+    outdoor: OutdoorSensorMode,
+}
+
+impl Modes {
+    const SIZE: usize = 2;
+
+    pub const fn new() -> Self {
+        Modes {
+            indoor: IndoorSensorMode::IndoorAirQuality,
+            outdoor: OutdoorSensorMode::OutdoorAirQuality,
+        }
+    }
+
+    fn to_bytes(self) -> [u8; Self::SIZE] {
+        [self.indoor as u8, self.outdoor as u8]
+    }
+
+    fn from_bytes(buf: &[u8; Self::SIZE]) -> Option<Self> {
+        Some(Modes {
+            indoor: IndoorSensorMode::try_from(buf[0]).ok()?,
+            outdoor: OutdoorSensorMode::try_from(buf[1]).ok()?,
+        })
+    }
+}
+
+/// Board on the I2C bus.
+struct Board {
+    address: u8,
+    sensor: Measurement,
+    /// Last I2C read, not available when reading via UART.
+    snapshot: Option<Snapshot>,
+    /// Error from the last read.
+    error: Option<Error>,
+}
+
+impl Board {
+    pub const fn new(address: u8) -> Self {
+        Board {
+            address,
+            sensor: Measurement::new(),
+            snapshot: None,
+            error: None,
+        }
+    }
+}
+
+struct PowerStatus {
     current: f32,
     battery_percentage: f32,
 }
 
-impl Measurement {
+impl PowerStatus {
     pub const fn new() -> Self {
-        Measurement {
-            temperature: 0.0,
-            humidity: 0.0,
-            epa_eqa: 0,
-            fast_eqa: 0,
-            o3: 0.0,
-            no2: 0.0,
-            eco2: 0.0,
-            tvoc: 0.0,
-            c2h6o: 0.0,
-            relative_iaq: 0.0,
+        PowerStatus {
             current: 0.0,
             battery_percentage: 0.0,
         }
@@ -71,16 +289,58 @@ entry!(main);
 
 /// View draw handler.
 /// Screen is 128x64 px
-unsafe extern "C" fn draw_callback(canvas: *mut sys::Canvas, _context: *mut c_void) {
-    let values = VALUES.lock();
+unsafe extern "C" fn draw_callback(canvas: *mut sys::Canvas, context: *mut c_void) {
+    let app: &App = &*(context.cast());
+    let page = PAGE.load(Ordering::Acquire);
+    let boards = BOARDS.lock();
+    let Some(board) = boards.get(page) else {
+        return;
+    };
+
+    let sensor = &board.sensor;
+    let power = POWER.lock();
+
+    let title = if USE_UART.load(Ordering::Acquire) && UART_BOARD.load(Ordering::Acquire) == page {
+        // Show UART link quality as the number of line errors
+        let errors = app.receiver.as_ref().map_or(0, |receiver| receiver.line_errors().total());
+        format!("Nicla 0x{:02X} ({}/{}) E:{}", board.address, page + 1, boards.len(), errors)
+    } else {
+        format!("Nicla 0x{:02X} ({}/{})", board.address, page + 1, boards.len())
+    };
+    let power_line = sprintf!(
+        c"draw: %0.0f mA, battery: %0.0f%%",
+        (-power.current * 1000.0) as c_double,
+        power.battery_percentage as c_double
+    );
+
+    if let Some(err) = board.error {
+        let lines = [
+            title,
+            format!("Error:"),
+            format!("{}", display(err)),
+            power_line,
+        ];
+
+        sys::canvas_set_font(canvas, sys::FontSecondary);
+        for (n, line) in lines.iter().enumerate() {
+            sys::canvas_draw_str(canvas, 0, (n + 1) as i32 * 9, line.as_c_str().as_ptr());
+        }
+
+        return;
+    }
+
+    let modes = *MODES.lock();
+    let [outdoor_1, outdoor_2] = outdoor_lines(modes.outdoor, sensor);
+    let [indoor_1, indoor_2] = indoor_lines(modes.indoor, board);
 
     let lines = [
-        format!("ARDUINO Nikla Sense ENV"),
-        sprintf!(c"%0.1f degC, Humid: %0.1f%%", values.temperature as c_double, values.humidity as c_double),
-        sprintf!(c"O3: %0.0f ppb, NO2: %0.0f ppb", values.o3 as c_double, values.no2 as c_double),
-        sprintf!(c"eCO2: %0.0f ppm, TVOC: %0.0f", values.eco2 as c_double, values.tvoc as c_double),
-        sprintf!(c"IAQ: %0.0f%%, C2H6O: %0.0f", values.relative_iaq as c_double, values.c2h6o as c_double),
-        sprintf!(c"draw: %0.0f mA, battery: %0.0f%%", (-values.current * 1000.0) as c_double, values.battery_percentage as c_double),
+        title,
+        sprintf!(c"%0.1f degC, Humid: %0.1f%%", sensor.temperature as c_double, sensor.humidity as c_double),
+        outdoor_1,
+        outdoor_2,
+        indoor_1,
+        indoor_2,
+        power_line,
     ];
 
     sys::canvas_set_font(canvas, sys::FontSecondary);
@@ -107,112 +367,1066 @@ unsafe extern "C" fn draw_callback(canvas: *mut sys::Canvas, _context: *mut c_vo
     );
 }
 
-struct MainView<'a> {
+/// Outdoor sensor lines of the main view.
+fn outdoor_lines(mode: OutdoorSensorMode, sensor: &Measurement) -> [FuriString; 2] {
+    match mode {
+        OutdoorSensorMode::OutdoorAirQuality => [
+            unsafe { sprintf!(c"O3: %0.0f ppb, NO2: %0.0f ppb", sensor.o3 as c_double, sensor.no2 as c_double) },
+            format!("EPA AQI: {}, fast AQI: {}", sensor.epa_aqi, sensor.fast_aqi),
+        ],
+        OutdoorSensorMode::Cleaning => [format!("Outdoor: cleaning"), FuriString::new()],
+        OutdoorSensorMode::Off => [format!("Outdoor: off"), FuriString::new()],
+    }
+}
+
+/// Indoor sensor lines of the main view.
+fn indoor_lines(mode: IndoorSensorMode, board: &Board) -> [FuriString; 2] {
+    let sensor = &board.sensor;
+
+    match mode {
+        IndoorSensorMode::IndoorAirQuality => [
+            unsafe { sprintf!(c"eCO2: %0.0f ppm, TVOC: %0.0f", sensor.eco2 as c_double, sensor.tvoc as c_double) },
+            unsafe { sprintf!(c"IAQ: %0.0f%%, C2H6O: %0.0f", sensor.relative_iaq as c_double, sensor.ethanol as c_double) },
+        ],
+        IndoorSensorMode::IndoorAirQualityLowPower => [
+            unsafe { sprintf!(c"IAQ: %0.1f (low power)", sensor.iaq as c_double) },
+            unsafe { sprintf!(c"eCO2: %0.0f ppm, TVOC: %0.2f", sensor.eco2 as c_double, sensor.tvoc as c_double) },
+        ],
+        IndoorSensorMode::PublicBuildingAirQuality => [
+            unsafe { sprintf!(c"PBAQ TVOC: %0.2f mg/m3", sensor.tvoc as c_double) },
+            unsafe { sprintf!(c"PBAQ C2H6O: %0.2f ppm", sensor.ethanol as c_double) },
+        ],
+        IndoorSensorMode::Sulfur => match &board.snapshot {
+            Some(snapshot) => [
+                unsafe { sprintf!(c"Odor intensity: %0.2f", snapshot.odor_intensity as c_double) },
+                format!("Odor class: {}", if snapshot.odor_class == 1 { "sulfur" } else { "acceptable" }),
+            ],
+            None => [format!("Odor: only via I2C"), FuriString::new()],
+        },
+        IndoorSensorMode::Cleaning => [format!("Indoor: cleaning"), FuriString::new()],
+        IndoorSensorMode::Off => [format!("Indoor: off"), FuriString::new()],
+    }
+}
+
+/// Settings view draw handler.
+unsafe extern "C" fn settings_draw_callback(canvas: *mut sys::Canvas, _context: *mut c_void) {
+    let settings = SETTINGS.lock();
+
+    sys::canvas_set_font(canvas, sys::FontPrimary);
+    sys::canvas_draw_str(canvas, 0, 9, c"Settings".as_ptr());
+
+    let items = [
+        format!("Baud rate: < {} >", nicla_sense_env::BAUD_RATE_MAP[settings.baud_index]),
+        format!("Persist settings"),
+        format!("Restore factory settings"),
+        format!("Source: < {} >", if USE_UART.load(Ordering::Acquire) { "UART" } else { "I2C" }),
+        format!("Address: < 0x{:02X} >", settings.address),
+    ];
+
+    sys::canvas_set_font(canvas, sys::FontSecondary);
+    for (n, item) in items.iter().enumerate() {
+        let y = 18 + n as i32 * 9;
+        if n == settings.selected {
+            sys::canvas_draw_str(canvas, 0, y, c">".as_ptr());
+        }
+        sys::canvas_draw_str(canvas, 8, y, item.as_c_str().as_ptr());
+    }
+
+    let status = match settings.status {
+        None => return,
+        Some(Ok(message)) => format!("{}", message),
+        Some(Err(err)) => format!("Error: {}", display(err)),
+    };
+    sys::canvas_draw_str(canvas, 0, 63, status.as_c_str().as_ptr());
+}
+
+/// Sensor mode view draw handler.
+unsafe extern "C" fn modes_draw_callback(canvas: *mut sys::Canvas, _context: *mut c_void) {
+    let menu = MODE_MENU.lock();
+
+    sys::canvas_set_font(canvas, sys::FontPrimary);
+    sys::canvas_draw_str(canvas, 0, 9, c"Sensor modes".as_ptr());
+
+    let items = [
+        format!("Indoor: < {} >", INDOOR_MODES[menu.indoor].1),
+        format!("Outdoor: < {} >", OUTDOOR_MODES[menu.outdoor].1),
+        format!("Clean: < {} >", if menu.clean_indoor { "Indoor" } else { "Outdoor" }),
+    ];
+
+    sys::canvas_set_font(canvas, sys::FontSecondary);
+    for (n, item) in items.iter().enumerate() {
+        let y = 18 + n as i32 * 9;
+        if n == menu.selected {
+            sys::canvas_draw_str(canvas, 0, y, c">".as_ptr());
+        }
+        sys::canvas_draw_str(canvas, 8, y, item.as_c_str().as_ptr());
+    }
+    let hint = if menu.selected == 2 { c"OK: clean this board" } else { c"OK: apply to all boards" };
+    sys::canvas_draw_str(canvas, 0, 45, hint.as_ptr());
+
+    let status = match menu.status {
+        None => return,
+        Some(Ok(message)) => format!("{}", message),
+        Some(Err(err)) => format!("Error: {}", display(err)),
+    };
+    sys::canvas_draw_str(canvas, 0, 63, status.as_c_str().as_ptr());
+}
+
+/// Diagnostics view draw handler.
+unsafe extern "C" fn diagnostics_draw_callback(canvas: *mut sys::Canvas, _context: *mut c_void) {
+    let page = PAGE.load(Ordering::Acquire);
+    let boards = BOARDS.lock();
+    let Some(board) = boards.get(page) else {
+        return;
+    };
+
+    sys::canvas_set_font(canvas, sys::FontSecondary);
+    let Some(snapshot) = &board.snapshot else {
+        sys::canvas_draw_str(canvas, 0, 9, c"Diagnostics need I2C".as_ptr());
+        return;
+    };
+
+    let (rmox, lines) = if DIAGNOSTICS_INDOOR.load(Ordering::Acquire) {
+        let [cda_0, cda_1, cda_2] = snapshot.indoor_rcda;
+        let lines = [
+            format!("ZMOD4410 0x{:02X}, status 0x{:02X}", board.address, snapshot.indoor_status),
+            sprintf!(c"Rhtr: %0.0f, T: %0.1f degC", snapshot.indoor_rhtr as c_double, snapshot.indoor_temp as c_double),
+            sprintf!(c"log Rcda: %0.2f %0.2f %0.2f", cda_0 as c_double, cda_1 as c_double, cda_2 as c_double),
+        ];
+
+        (&snapshot.indoor_rmox, lines)
+    } else {
+        let lines = [
+            format!("ZMOD4510 0x{:02X}, status 0x{:02X}", board.address, snapshot.outdoor_status),
+            FuriString::new(),
+            FuriString::new(),
+        ];
+
+        (&snapshot.outdoor_rmox, lines)
+    };
+
+    let [title, line_1, line_2] = lines;
+    sys::canvas_draw_str(canvas, 0, 9, title.as_c_str().as_ptr());
+    sys::canvas_draw_str(canvas, 0, 54, line_1.as_c_str().as_ptr());
+    sys::canvas_draw_str(canvas, 0, 63, line_2.as_c_str().as_ptr());
+
+    draw_rmox_chart(Canvas::from_raw_mut(canvas), rmox).unwrap();
+}
+
+/// Draw Rmox resistances as a bar chart on a log scale.
+///
+/// Ticks on the left mark each decade of [`RMOX_LOG_RANGE`].
+fn draw_rmox_chart<D>(display: &mut D, rmox: &[f32; 13]) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    const TOP: i32 = 12;
+    const HEIGHT: u32 = 33;
+    const BAR_WIDTH: u32 = 7;
+    const BAR_SPACING: i32 = 9;
+    const LEFT: i32 = 4;
+
+    let stroke = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+    let fill = PrimitiveStyle::with_fill(BinaryColor::On);
+    let bottom = TOP + HEIGHT as i32;
+
+    // Axes
+    Line::new(Point::new(LEFT - 2, TOP), Point::new(LEFT - 2, bottom))
+        .into_styled(stroke)
+        .draw(display)?;
+    Line::new(Point::new(LEFT - 2, bottom), Point::new(LEFT + 13 * BAR_SPACING, bottom))
+        .into_styled(stroke)
+        .draw(display)?;
+
+    let decades = (RMOX_LOG_RANGE.end() - RMOX_LOG_RANGE.start()) as i32;
+    for decade in 0..=decades {
+        let y = bottom - decade * HEIGHT as i32 / decades;
+        Line::new(Point::new(0, y), Point::new(LEFT - 2, y))
+            .into_styled(stroke)
+            .draw(display)?;
+    }
+
+    for (n, &resistance) in rmox.iter().enumerate() {
+        let height = bar_height(resistance, HEIGHT);
+        if height == 0 {
+            continue;
+        }
+
+        let x = LEFT + n as i32 * BAR_SPACING;
+        Rectangle::new(Point::new(x, bottom - height as i32), Size::new(BAR_WIDTH, height))
+            .into_styled(fill)
+            .draw(display)?;
+    }
+
+    Ok(())
+}
+
+/// Height of the bar for `resistance` (Ohm), on a log scale covering [`RMOX_LOG_RANGE`].
+fn bar_height(resistance: f32, max_height: u32) -> u32 {
+    if resistance.is_nan() || resistance <= 0.0 {
+        return 0;
+    }
+
+    let (min, max) = (*RMOX_LOG_RANGE.start(), *RMOX_LOG_RANGE.end());
+    let scaled = (libm::log10f(resistance) - min) / (max - min);
+
+    (scaled.clamp(0.0, 1.0) * max_height as f32) as u32
+}
+
+/// Cleaning view draw handler.
+unsafe extern "C" fn cleaning_draw_callback(canvas: *mut sys::Canvas, _context: *mut c_void) {
+    let cleaning = CLEANING.lock();
+    let Some(cleaning) = cleaning.as_ref() else {
+        return;
+    };
+
+    sys::canvas_set_font(canvas, sys::FontPrimary);
+    sys::canvas_draw_str(canvas, 0, 9, c"MOx cleaning".as_ptr());
+
+    sys::canvas_set_font(canvas, sys::FontSecondary);
+    let sensor = if cleaning.indoor { "ZMOD4410" } else { "ZMOD4510" };
+    let title = format!("{} on 0x{:02X}", sensor, cleaning.address);
+    sys::canvas_draw_str(canvas, 0, 18, title.as_c_str().as_ptr());
+
+    let status = match cleaning.result {
+        Some(Ok(message)) => format!("{}", message),
+        Some(Err(err)) => format!("Error: {}", display(err)),
+        None => {
+            let elapsed = format!("Elapsed: {} s", rtc_timestamp().saturating_sub(cleaning.started));
+            sys::canvas_draw_str(canvas, 0, 27, elapsed.as_c_str().as_ptr());
+
+            if let Some(zmod_status) = cleaning.zmod_status {
+                let line = format!("ZMOD status: 0x{:02X}", zmod_status);
+                sys::canvas_draw_str(canvas, 0, 54, line.as_c_str().as_ptr());
+            }
+
+            if cleaning.warned {
+                format!("Back again to abort!")
+            } else {
+                format!("Do not disconnect")
+            }
+        },
+    };
+    sys::canvas_draw_str(canvas, 0, 63, status.as_c_str().as_ptr());
+}
+
+/// Driver for a board on the shared bus.
+type Device<'a> = NiclaSenseEnv<&'a mut I2cBus, Delay>;
+
+type CsvCallback = fn(SerialEvent<'_>);
+type CsvReceiver<'a> = AsyncSerialReceiver<'a, CsvCallback>;
+
+struct App {
     power: *mut sys::Power,
-    device: *mut NiclaSenseEnv<'a>,
+    storage: *const Record<Storage>,
+    bus: *mut I2cBus,
+    /// May be null if the serial interface is unavailable.
+    serial: *const SerialHandle,
+    /// Receiver of the UART CSV stream, null if `serial` is.
+    receiver: *const CsvReceiver<'static>,
+    view_dispatcher: *mut sys::ViewDispatcher,
+}
+
+impl App {
+    /// Driver for the board at `address`.
+    unsafe fn device(&self, address: u8) -> Device<'_> {
+        NiclaSenseEnv::new(&mut *self.bus, Delay, address)
+    }
 }
 
 unsafe extern "C" fn tick_callback(ctx: *mut c_void) {
-    let context: &mut MainView = &mut *(ctx.cast());
-    let device = &mut *context.device;
+    let app: &mut App = &mut *(ctx.cast());
 
     let mut power_info = mem::zeroed();
-    sys::power_get_info(context.power, &raw mut power_info);
-    let battery_percentage = 100.0 * (power_info.capacity_remaining as f32 / power_info.capacity_full as f32);
-
-    let measurement =
-    if device.is_ready() {
-        Measurement {
-            temperature: device.temperature(),
-            humidity: device.humidity(),
-            epa_eqa: device.outdoor_epa_aqi(),
-            fast_eqa: device.outdoor_fast_aqi(),
-            o3: device.outdoor_o3(),
-            no2: device.outdoor_no2(),
-            eco2: device.indoor_estimated_co2(),
-            tvoc: device.indoor_total_voc(),
-            c2h6o: device.indoor_ethanol(),
-            relative_iaq: device.indoor_relative_iqa(),
-            current: power_info.current_gauge,
-            battery_percentage,
+    sys::power_get_info(app.power, &raw mut power_info);
+
+    {
+        let mut power = POWER.lock();
+        power.current = power_info.current_gauge;
+        power.battery_percentage =
+            100.0 * (power_info.capacity_remaining as f32 / power_info.capacity_full as f32);
+    }
+
+    let use_uart = USE_UART.load(Ordering::Acquire);
+    let uart_board = UART_BOARD.load(Ordering::Acquire);
+    let mut new_samples = false;
+
+    // Copy the addresses so that the lock isn't held during I2C transfers, which would stall drawing
+    let addresses: heapless::Vec<(usize, u8), MAX_BOARDS> = BOARDS
+        .lock()
+        .iter()
+        .enumerate()
+        // Measurements arrive via `on_csv_data` instead
+        .filter(|&(index, _)| !(use_uart && index == uart_board))
+        .map(|(index, board)| (index, board.address))
+        .collect();
+
+    for (index, address) in addresses {
+        let mut device = app.device(address);
+        let result = if device.is_ready() {
+            device.read_all()
+        } else {
+            Err(NiclaError::NotReady)
+        };
+
+        let mut boards = BOARDS.lock();
+        let Some(board) = boards.get_mut(index).filter(|board| board.address == address) else {
+            continue;
+        };
+
+        match result {
+            Ok(snapshot) if !snapshot.temperature_ready() => {
+                board.error = Some(NiclaError::NotReady.into());
+            },
+            Ok(snapshot) => {
+                new_samples |= board.snapshot.as_ref().is_none_or(|previous| snapshot.has_new_samples(&previous.samples));
+                board.sensor = snapshot.measurement;
+                board.snapshot = Some(snapshot);
+                board.error = None;
+            },
+            Err(err) => {
+                println!("failed to read sensor 0x{:02X}: {}", address, display(err));
+                board.error = Some(err.into());
+            },
         }
+    }
+
+    if new_samples {
+        SAMPLE_COUNT.fetch_add(1, Ordering::AcqRel);
+    }
+
+    poll_cleaning(app);
+}
+
+/// Current RTC time (seconds since UNIX epoch).
+fn rtc_timestamp() -> u32 {
+    unsafe { sys::furi_hal_rtc_get_timestamp() }
+}
+
+/// Switch a sensor of the board at `address` to cleaning mode.
+unsafe fn start_cleaning(app: &mut App, address: u8, indoor: bool) -> Result<(), Error> {
+    let mut device = app.device(address);
+    let previous = Modes {
+        indoor: device.indoor_sensor_mode()?,
+        outdoor: device.outdoor_sensor_mode()?,
+    };
+
+    if indoor {
+        device.set_indoor_sensor_mode(IndoorSensorMode::Cleaning)?;
     } else {
-        Measurement {
-            temperature: 0.0,
-            humidity: 0.0,
-            epa_eqa: 0,
-            fast_eqa: 0,
-            o3: 0.0,
-            no2: 0.0,
-            eco2: 0.0,
-            tvoc: 0.0,
-            c2h6o: 0.0,
-            relative_iaq: 0.0,
-            current: power_info.current_gauge,
-            battery_percentage,
+        device.set_outdoor_sensor_mode(OutdoorSensorMode::Cleaning)?;
+    }
+
+    *CLEANING.lock() = Some(Cleaning {
+        indoor,
+        address,
+        previous,
+        started: rtc_timestamp(),
+        zmod_status: None,
+        sequencer_ran: false,
+        warned: false,
+        result: None,
+    });
+
+    Ok(())
+}
+
+/// Check whether the cleaning cycle has finished and if so restore the previous mode.
+///
+/// Cleaning is finished once the board leaves cleaning mode by itself, or the ZMOD sequencer has
+/// stopped after having run.
+unsafe fn poll_cleaning(app: &mut App) {
+    // Copy the state so that the lock isn't held during I2C transfers, which would stall drawing
+    let (address, indoor, previous, started, sequencer_ran) = {
+        let cleaning = CLEANING.lock();
+        let Some(cleaning) = cleaning.as_ref().filter(|cleaning| cleaning.result.is_none()) else {
+            return;
+        };
+
+        (cleaning.address, cleaning.indoor, cleaning.previous, cleaning.started, cleaning.sequencer_ran)
+    };
+
+    let mut device = app.device(address);
+
+    let polled = cleaning_status(&mut device, indoor);
+    let finished = match polled {
+        Ok((zmod_status, still_cleaning)) => {
+            !still_cleaning || (sequencer_ran && zmod_status & ZMOD_STATUS_SEQUENCER_RUNNING == 0)
+        },
+        Err(err) => {
+            println!("failed to poll cleaning: {}", display(err));
+            false
+        },
+    };
+    let timed_out = rtc_timestamp().saturating_sub(started) >= CLEANING_TIMEOUT_SECS;
+
+    let result = (finished || timed_out).then(|| {
+        let message = if finished { "Cleaning complete" } else { "Cleaning timed out" };
+        restore_mode(&mut device, indoor, previous).map(|_| message).map_err(Error::from)
+    });
+
+    let status = {
+        let mut cleaning = CLEANING.lock();
+        // Cleaning may have been aborted in the meantime
+        let Some(cleaning) = cleaning.as_mut().filter(|cleaning| cleaning.result.is_none()) else {
+            return;
+        };
+
+        if let Ok((zmod_status, _)) = polled {
+            cleaning.zmod_status = Some(zmod_status);
+            cleaning.sequencer_ran |= zmod_status & ZMOD_STATUS_SEQUENCER_RUNNING != 0;
         }
+
+        let Some(result) = result else {
+            return;
+        };
+        cleaning.result = Some(result);
+
+        cleaning.result
     };
+    MODE_MENU.lock().status = status;
+}
 
-    *VALUES.lock() = measurement;
-    SAMPLE_COUNT.fetch_add(1, Ordering::AcqRel);
+/// Read the ZMOD status register and whether the sensor is still in cleaning mode.
+fn cleaning_status(device: &mut Device, indoor: bool) -> Result<(u8, bool), NiclaError> {
+    if indoor {
+        Ok((device.indoor_status()?, device.indoor_sensor_mode()? == IndoorSensorMode::Cleaning))
+    } else {
+        Ok((device.outdoor_status()?, device.outdoor_sensor_mode()? == OutdoorSensorMode::Cleaning))
+    }
+}
+
+/// Switch a sensor back to its mode from before cleaning.
+fn restore_mode(device: &mut Device, indoor: bool, previous: Modes) -> Result<(), NiclaError> {
+    if indoor {
+        let mode = match previous.indoor {
+            IndoorSensorMode::Cleaning => IndoorSensorMode::default(),
+            mode => mode,
+        };
+        device.set_indoor_sensor_mode(mode)
+    } else {
+        let mode = match previous.outdoor {
+            OutdoorSensorMode::Cleaning => OutdoorSensorMode::default(),
+            mode => mode,
+        };
+        device.set_outdoor_sensor_mode(mode)
+    }
+}
+
+/// Handle an event from the UART CSV stream.
+fn on_csv_event(event: SerialEvent<'_>) {
+    let mut decoder = CSV_DECODER.lock();
+
+    match event {
+        SerialEvent::Data(data) => on_csv_data(&mut decoder, data),
+        SerialEvent::Error(err) => {
+            // The current line is missing or has corrupted bytes
+            decoder.reset();
+            println!("UART {}", display(err));
+        },
+        SerialEvent::Idle => {},
+    }
+}
+
+/// Handle data received from the UART CSV stream.
+fn on_csv_data(decoder: &mut CsvDecoder, data: &[u8]) {
+    if !USE_UART.load(Ordering::Acquire) {
+        decoder.reset();
+        return;
+    }
+
+    decoder.set_delimiter(CSV_DELIMITER.load(Ordering::Acquire));
+    for result in decoder.decode(data) {
+        let mut boards = BOARDS.lock();
+        let Some(board) = boards.get_mut(UART_BOARD.load(Ordering::Acquire)) else {
+            continue;
+        };
+
+        match result {
+            Ok(measurement) => {
+                board.sensor = measurement;
+                board.snapshot = None;
+                board.error = None;
+                SAMPLE_COUNT.fetch_add(1, Ordering::AcqRel);
+            },
+            Err(err) => {
+                println!("bad CSV line: {}", display(err));
+                board.error = Some(Error::Csv(err));
+            },
+        }
+    }
+}
+
+/// Switch between reading measurements over I2C and the UART CSV stream.
+///
+/// Enabling UART configures the board over I2C if possible, otherwise the board's persisted settings are used.
+unsafe fn set_use_uart(app: &mut App, address: u8, use_uart: bool) -> Result<&'static str, Error> {
+    if !use_uart {
+        let uart_address = BOARDS.lock()[UART_BOARD.load(Ordering::Acquire)].address;
+        // Keep reading via UART if the board still sends CSV lines
+        app.device(uart_address).set_csv_output_enabled(false)?;
+        USE_UART.store(false, Ordering::Release);
+
+        return Ok("Reading via I2C");
+    }
+
+    if app.serial.is_null() {
+        return Err(Error::UartBusy);
+    }
+
+    let mut device = app.device(address);
+
+    // Only TX/RX may be wired, in which case the current delimiter and baud rate are kept
+    if let Err(err) = device.set_csv_output_enabled(true) {
+        println!("failed to enable CSV output: {}", display(err));
+    }
+    let delimiter = device.csv_delimiter().unwrap_or(CSV_DELIMITER.load(Ordering::Acquire));
+    if let Ok(baud_rate) = device.uart_baud_rate() {
+        (*app.serial).set_baud_rate(baud_rate);
+    }
+
+    CSV_DELIMITER.store(delimiter, Ordering::Release);
+    if let Some(receiver) = app.receiver.as_ref() {
+        receiver.clear_line_errors();
+    }
+    UART_BOARD.store(PAGE.load(Ordering::Acquire), Ordering::Release);
+    USE_UART.store(true, Ordering::Release);
+
+    Ok("Reading via UART")
+}
+
+/// Move the board at `index` to a new address.
+unsafe fn set_address(app: &mut App, index: usize, address: u8) -> Result<&'static str, Error> {
+    // Don't hold the lock during I2C transfers, which would stall drawing
+    let previous = {
+        let boards = BOARDS.lock();
+        if boards.iter().any(|board| board.address == address) {
+            return Err(Error::AddressInUse(address));
+        }
+
+        boards[index].address
+    };
+
+    app.device(previous).set_device_address(address, false)?;
+    BOARDS.lock()[index].address = address;
+
+    Ok("Address changed")
+}
+
+/// Find boards on the bus.
+///
+/// Falls back to the default address if no board responds, so that errors are shown.
+fn scan(bus: &mut I2cBus) -> heapless::Vec<Board, MAX_BOARDS> {
+    let mut boards = heapless::Vec::new();
+
+    for address in nicla_sense_env::VALID_ADDRESSES {
+        let mut device = NiclaSenseEnv::new(&mut *bus, Delay, address);
+        if !device.is_ready() || device.product_id() != Ok(nicla_sense_env::PRODUCT_ID) {
+            continue;
+        }
+
+        println!("Found board at 0x{:02X}", address);
+        if boards.push(Board::new(address)).is_err() {
+            break;
+        }
+    }
+
+    if boards.is_empty() {
+        boards.push(Board::new(nicla_sense_env::DEFAULT_DEVICE_ADDRESS)).ok();
+    }
+
+    boards
+}
+
+/// Set the sensor modes of all boards and remember them for the next run.
+///
+/// Boards that fail are skipped and the first error is returned. A mode is remembered if any board accepted it.
+unsafe fn apply_modes(app: &mut App, modes: Modes) -> Result<&'static str, Error> {
+    let addresses: heapless::Vec<u8, MAX_BOARDS> = BOARDS.lock().iter().map(|board| board.address).collect();
+
+    let mut applied = *MODES.lock();
+    let mut first_error = None;
+    for address in addresses {
+        let mut device = app.device(address);
+
+        let outdoor = device.set_outdoor_sensor_mode(modes.outdoor);
+        if outdoor.is_ok() {
+            applied.outdoor = modes.outdoor;
+        }
+        let indoor = device.set_indoor_sensor_mode(modes.indoor);
+        if indoor.is_ok() {
+            applied.indoor = modes.indoor;
+        }
+
+        for err in [outdoor, indoor].into_iter().filter_map(Result::err) {
+            println!("failed to set modes of 0x{:02X}: {}", address, display(err));
+            first_error.get_or_insert(Error::from(err));
+        }
+    }
+    *MODES.lock() = applied;
+
+    if !save_modes(&*app.storage, applied) {
+        return Err(Error::Storage);
+    }
+
+    match first_error {
+        Some(err) => Err(err),
+        None => Ok("Modes applied"),
+    }
+}
+
+/// Load the sensor modes saved by the last run.
+fn load_modes(storage: &Record<Storage>) -> Option<Modes> {
+    let mut file = storage.open_file(MODES_PATH, sys::FSAM_READ, sys::FSOM_OPEN_EXISTING)?;
+
+    let mut buf = [0u8; Modes::SIZE];
+    if file.read(&mut buf) != buf.len() {
+        println!("ERROR: saved modes are truncated");
+        return None;
+    }
+
+    let modes = Modes::from_bytes(&buf);
+    if modes.is_none() {
+        println!("ERROR: saved modes are invalid");
+    }
+
+    modes
+}
+
+/// Save sensor modes to storage.
+fn save_modes(storage: &Record<Storage>, modes: Modes) -> bool {
+    if !storage.simply_mkdir(MODES_DIR) {
+        return false;
+    }
+
+    let Some(mut file) = storage.open_file(MODES_PATH, sys::FSAM_WRITE, sys::FSOM_CREATE_ALWAYS) else {
+        return false;
+    };
+
+    let data = modes.to_bytes();
+    file.write(&data) == data.len()
+}
+
+/// Configure sensor modes and LEDs.
+fn configure(device: &mut Device, modes: Modes) -> Result<(), NiclaError> {
+    println!("Setting outdoor sensor mode");
+    device.set_outdoor_sensor_mode(modes.outdoor)?;
+
+    println!("Setting indoor sensor mode");
+    device.set_indoor_sensor_mode(modes.indoor)?;
+
+    device.set_orange_led(OrangeLed::new(0, true))?; // Enable sensor error warning
+    device.set_rgb_intensity(0)?;
+
+    Ok(())
+}
+
+/// Main view input handler.
+unsafe extern "C" fn input_callback(event: *mut sys::InputEvent, ctx: *mut c_void) -> bool {
+    let app: &mut App = &mut *(ctx.cast());
+    let event = &*event;
+
+    if event.type_ != sys::InputTypeShort {
+        return false;
+    }
+
+    let page = PAGE.load(Ordering::Acquire);
+    let pages = BOARDS.lock().len();
+    match event.key {
+        sys::InputKeyLeft => PAGE.store((page + pages - 1) % pages, Ordering::Release),
+        sys::InputKeyRight => PAGE.store((page + 1) % pages, Ordering::Release),
+        sys::InputKeyUp => {
+            let modes = *MODES.lock();
+            let mut menu = MODE_MENU.lock();
+            menu.indoor = modes.indoor as usize;
+            menu.outdoor = modes.outdoor as usize;
+            menu.status = None;
+
+            sys::view_dispatcher_switch_to_view(app.view_dispatcher, MODES_VIEW.0);
+        },
+        sys::InputKeyDown => sys::view_dispatcher_switch_to_view(app.view_dispatcher, DIAGNOSTICS_VIEW.0),
+        sys::InputKeyOk => {
+            let mut settings = SETTINGS.lock();
+            settings.confirm_reset = false;
+            settings.status = None;
+
+            let address = BOARDS.lock()[page].address;
+            settings.address = address;
+
+            match app.device(address).uart_baud_rate() {
+                Ok(baud_rate) => {
+                    settings.baud_index = nicla_sense_env::BAUD_RATE_MAP.iter().position(|&b| b == baud_rate).unwrap_or(0);
+                },
+                Err(err) => settings.status = Some(Err(err.into())),
+            }
+
+            sys::view_dispatcher_switch_to_view(app.view_dispatcher, SETTINGS_VIEW.0);
+        },
+        _ => return false,
+    }
+
+    true
+}
+
+/// Settings view input handler.
+unsafe extern "C" fn settings_input_callback(event: *mut sys::InputEvent, ctx: *mut c_void) -> bool {
+    let app: &mut App = &mut *(ctx.cast());
+    let event = &*event;
+
+    if event.type_ != sys::InputTypeShort {
+        return false;
+    }
+
+    let page = PAGE.load(Ordering::Acquire);
+    let address = BOARDS.lock()[page].address;
+
+    let baud_rates = nicla_sense_env::BAUD_RATE_MAP.len();
+    let mut settings = SETTINGS.lock();
+    if settings.confirm_reset && !(event.key == sys::InputKeyOk && settings.selected == 2) {
+        settings.confirm_reset = false;
+        settings.status = None;
+    }
+
+    match event.key {
+        sys::InputKeyUp => settings.selected = (settings.selected + SETTINGS_ITEMS - 1) % SETTINGS_ITEMS,
+        sys::InputKeyDown => settings.selected = (settings.selected + 1) % SETTINGS_ITEMS,
+        sys::InputKeyLeft if settings.selected == 0 => {
+            settings.baud_index = (settings.baud_index + baud_rates - 1) % baud_rates;
+        },
+        sys::InputKeyRight if settings.selected == 0 => {
+            settings.baud_index = (settings.baud_index + 1) % baud_rates;
+        },
+        sys::InputKeyLeft | sys::InputKeyRight | sys::InputKeyOk if settings.selected == 3 => {
+            let result = set_use_uart(app, address, !USE_UART.load(Ordering::Acquire));
+            if let Err(err) = result {
+                println!("failed to switch source: {}", display(err));
+            }
+            settings.status = Some(result);
+        },
+        sys::InputKeyLeft if settings.selected == 4 => {
+            let range = nicla_sense_env::VALID_ADDRESSES;
+            settings.address = if settings.address > *range.start() { settings.address - 1 } else { *range.end() };
+        },
+        sys::InputKeyRight if settings.selected == 4 => {
+            let range = nicla_sense_env::VALID_ADDRESSES;
+            settings.address = if settings.address < *range.end() { settings.address + 1 } else { *range.start() };
+        },
+        sys::InputKeyOk if settings.selected == 4 => {
+            let result = set_address(app, page, settings.address);
+            if let Err(err) = result {
+                println!("failed to change address: {}", display(err));
+            }
+            settings.status = Some(result);
+        },
+        sys::InputKeyOk if settings.selected == 0 => {
+            let baud_rate = nicla_sense_env::BAUD_RATE_MAP[settings.baud_index];
+            let result = app
+                .device(address)
+                .set_baud_rate(baud_rate, false)
+                .map(|_| {
+                    if !app.serial.is_null() {
+                        (*app.serial).set_baud_rate(baud_rate);
+                    }
+                    "Baud rate set"
+                })
+                .map_err(Error::from);
+
+            if let Err(err) = result {
+                println!("settings failed: {}", display(err));
+            }
+            settings.status = Some(result);
+        },
+        sys::InputKeyOk if settings.selected == 2 && !settings.confirm_reset => {
+            settings.confirm_reset = true;
+            settings.status = Some(Ok("OK again to reset board"));
+        },
+        sys::InputKeyOk => {
+            // Waiting for the board can take seconds, so show progress and finish in `custom_event_callback`
+            let event = if settings.selected == 1 { EVENT_PERSIST_SETTINGS } else { EVENT_FACTORY_RESET };
+            settings.confirm_reset = false;
+            settings.status = Some(Ok("Working..."));
+            sys::view_dispatcher_send_custom_event(app.view_dispatcher, event);
+        },
+        _ => return false,
+    }
+
+    true
+}
+
+/// Custom event handler for actions started from the settings view.
+unsafe extern "C" fn custom_event_callback(ctx: *mut c_void, event: u32) -> bool {
+    let app: &mut App = &mut *(ctx.cast());
+
+    let address = BOARDS.lock()[PAGE.load(Ordering::Acquire)].address;
+    let mut device = app.device(address);
+    let result = match event {
+        EVENT_PERSIST_SETTINGS => device.persist_settings().map(|_| "Settings persisted"),
+        EVENT_FACTORY_RESET => device.restore_factory_settings().map(|_| "Factory settings restored"),
+        _ => return false,
+    }
+    .map_err(Error::from);
+
+    if let Err(err) = result {
+        println!("settings failed: {}", display(err));
+    }
+    SETTINGS.lock().status = Some(result);
+
+    true
+}
+
+/// Sensor mode view input handler.
+unsafe extern "C" fn modes_input_callback(event: *mut sys::InputEvent, ctx: *mut c_void) -> bool {
+    let app: &mut App = &mut *(ctx.cast());
+    let event = &*event;
+
+    if event.type_ != sys::InputTypeShort {
+        return false;
+    }
+
+    let indoor_modes = INDOOR_MODES.len();
+    let outdoor_modes = OUTDOOR_MODES.len();
+    let mut menu = MODE_MENU.lock();
+    match event.key {
+        sys::InputKeyUp => menu.selected = (menu.selected + MODE_ITEMS - 1) % MODE_ITEMS,
+        sys::InputKeyDown => menu.selected = (menu.selected + 1) % MODE_ITEMS,
+        sys::InputKeyLeft if menu.selected == 0 => menu.indoor = (menu.indoor + indoor_modes - 1) % indoor_modes,
+        sys::InputKeyRight if menu.selected == 0 => menu.indoor = (menu.indoor + 1) % indoor_modes,
+        sys::InputKeyLeft | sys::InputKeyRight if menu.selected == 2 => menu.clean_indoor = !menu.clean_indoor,
+        sys::InputKeyOk if menu.selected == 2 => {
+            let address = BOARDS.lock()[PAGE.load(Ordering::Acquire)].address;
+            match start_cleaning(app, address, menu.clean_indoor) {
+                Ok(()) => {
+                    menu.status = None;
+                    sys::view_dispatcher_switch_to_view(app.view_dispatcher, CLEANING_VIEW.0);
+                },
+                Err(err) => {
+                    println!("failed to start cleaning: {}", display(err));
+                    menu.status = Some(Err(err));
+                },
+            }
+        },
+        sys::InputKeyLeft => menu.outdoor = (menu.outdoor + outdoor_modes - 1) % outdoor_modes,
+        sys::InputKeyRight => menu.outdoor = (menu.outdoor + 1) % outdoor_modes,
+        sys::InputKeyOk => {
+            let modes = Modes {
+                indoor: INDOOR_MODES[menu.indoor].0,
+                outdoor: OUTDOOR_MODES[menu.outdoor].0,
+            };
+
+            let result = apply_modes(app, modes);
+            if let Err(err) = result {
+                println!("failed to set modes: {}", display(err));
+            }
+            menu.status = Some(result);
+        },
+        _ => return false,
+    }
+
+    true
+}
+
+/// Cleaning view input handler.
+///
+/// Leaving while cleaning requires pressing Back twice, which aborts the cycle.
+unsafe extern "C" fn cleaning_input_callback(event: *mut sys::InputEvent, ctx: *mut c_void) -> bool {
+    let app: &mut App = &mut *(ctx.cast());
+    let event = &*event;
+
+    if event.type_ != sys::InputTypeShort || event.key != sys::InputKeyBack {
+        return false;
+    }
+
+    let (address, indoor, previous) = {
+        let mut cleaning = CLEANING.lock();
+        let Some(cleaning) = cleaning.as_mut().filter(|cleaning| cleaning.result.is_none()) else {
+            return false;
+        };
+
+        if !cleaning.warned {
+            cleaning.warned = true;
+            return true;
+        }
+
+        (cleaning.address, cleaning.indoor, cleaning.previous)
+    };
+
+    let result = restore_mode(&mut app.device(address), indoor, previous).map(|_| "Cleaning aborted");
+    if let Err(err) = result {
+        println!("failed to restore mode: {}", display(err));
+    }
+
+    let status = {
+        let mut cleaning = CLEANING.lock();
+        let Some(cleaning) = cleaning.as_mut().filter(|cleaning| cleaning.result.is_none()) else {
+            return false;
+        };
+        cleaning.result = Some(result.map_err(Error::from));
+
+        cleaning.result
+    };
+    MODE_MENU.lock().status = status;
+
+    false
+}
+
+/// Diagnostics view input handler.
+unsafe extern "C" fn diagnostics_input_callback(event: *mut sys::InputEvent, _ctx: *mut c_void) -> bool {
+    let event = &*event;
+
+    if event.type_ != sys::InputTypeShort {
+        return false;
+    }
+
+    match event.key {
+        sys::InputKeyLeft | sys::InputKeyRight => {
+            DIAGNOSTICS_INDOOR.fetch_xor(true, Ordering::AcqRel);
+        },
+        _ => return false,
+    }
+
+    true
 }
 
 unsafe extern "C" fn back(ctx: *mut c_void) -> u32 {
-    sys::view_dispatcher_stop(ctx.cast());
+    let app: &mut App = &mut *(ctx.cast());
+    sys::view_dispatcher_stop(app.view_dispatcher);
 
     0
 }
 
+unsafe extern "C" fn settings_back(_ctx: *mut c_void) -> u32 {
+    MAIN_VIEW.0
+}
+
+unsafe extern "C" fn modes_back(_ctx: *mut c_void) -> u32 {
+    MAIN_VIEW.0
+}
+
+unsafe extern "C" fn diagnostics_back(_ctx: *mut c_void) -> u32 {
+    MAIN_VIEW.0
+}
+
+unsafe extern "C" fn cleaning_back(_ctx: *mut c_void) -> u32 {
+    MODES_VIEW.0
+}
+
 // Entry point
 fn main(_args: Option<&CStr>) -> i32 {
-    let mut bus = i2c::Bus::EXTERNAL.acquire();
-    let mut device = NiclaSenseEnv::with_default_addr(&mut bus);
+    let storage = Record::<Storage>::open();
+    let modes = load_modes(&storage).unwrap_or(Modes::new());
+    *MODES.lock() = modes;
+
+    let mut bus = I2cBus::external();
+
+    let boards = scan(&mut bus);
+    for board in &boards {
+        let mut device = NiclaSenseEnv::new(&mut bus, Delay, board.address);
+        if let Err(err) = configure(&mut device, modes) {
+            println!("failed to configure sensor 0x{:02X}: {}", board.address, display(err));
+        }
+    }
+
+    let uart_baud = NiclaSenseEnv::new(&mut bus, Delay, boards[0].address)
+        .uart_baud_rate()
+        .unwrap_or(DEFAULT_UART_BAUD);
+    *BOARDS.lock() = boards;
 
     // Power Setup
     let power= Record::<Power>::open();
 
-    let mut context = MainView {
-        power: power.as_ptr(),
-        device: &raw mut device,
-    };
-
     // GUI Setup
     let gui = Record::<Gui>::open();
 
+    // UART Setup
+    let serial = SerialHandle::acquire(UART_CHANNEL).ok();
+    if let Some(serial) = &serial {
+        serial.init(uart_baud);
+    }
+
+    let mut receiver = serial
+        .as_ref()
+        .map(|serial| serial.dma_event_receiver(on_csv_event as CsvCallback));
+    if let Some(receiver) = &mut receiver {
+        receiver.start();
+    }
+
     let view_dispatcher = ViewDispatcher::new();
+
+    let mut context = App {
+        power: power.as_ptr(),
+        storage: &raw const *storage,
+        bus: &raw mut bus,
+        serial: serial.as_ref().map_or(ptr::null(), |serial| serial as *const _),
+        receiver: receiver.as_ref().map_or(ptr::null(), |receiver| (receiver as *const CsvReceiver).cast()),
+        view_dispatcher: view_dispatcher.as_ptr(),
+    };
+
     unsafe {
         view_dispatcher.set_event_callback_context(&raw mut context as *mut _);
+        view_dispatcher.set_custom_event_callback(Some(custom_event_callback));
         view_dispatcher.set_tick_event_callback(Some(tick_callback), FuriDuration::from_millis(500));
     }
     view_dispatcher.attach_to_gui(&gui, sys::ViewDispatcherTypeFullscreen);
 
-    const MAIN_VIEW: ViewId = ViewId(0);
     let view = View::new();
     unsafe {
-        view.set_context(view_dispatcher.as_ptr().cast());
+        view.set_context(&raw mut context as *mut _);
         view.set_draw_callback(Some(draw_callback));
+        view.set_input_callback(Some(input_callback));
         view.set_previous_callback(Some(back));
     }
 
-    view_dispatcher.add_view(MAIN_VIEW, &view);
-    view_dispatcher.switch_to_view(MAIN_VIEW);
+    let settings_view = View::new();
+    unsafe {
+        settings_view.set_context(&raw mut context as *mut _);
+        settings_view.set_draw_callback(Some(settings_draw_callback));
+        settings_view.set_input_callback(Some(settings_input_callback));
+        settings_view.set_previous_callback(Some(settings_back));
+    }
 
-    println!("Setting outdoor sensor mode");
-    if !device.set_outdoor_sensor_mode(OutdoorSensorMode::OutdoorAirQuality) {
-        println!("failed to set outdoor sensor mode");
+    let modes_view = View::new();
+    unsafe {
+        modes_view.set_context(&raw mut context as *mut _);
+        modes_view.set_draw_callback(Some(modes_draw_callback));
+        modes_view.set_input_callback(Some(modes_input_callback));
+        modes_view.set_previous_callback(Some(modes_back));
     }
 
-    println!("Setting indoor sensor mode");
-    if !device.set_indoor_sensor_mode(IndoorSensorMode::IndoorAirQuality) {
-        println!("failed to set indoor sensor mode");
+    let diagnostics_view = View::new();
+    unsafe {
+        diagnostics_view.set_context(&raw mut context as *mut _);
+        diagnostics_view.set_draw_callback(Some(diagnostics_draw_callback));
+        diagnostics_view.set_input_callback(Some(diagnostics_input_callback));
+        diagnostics_view.set_previous_callback(Some(diagnostics_back));
     }
 
-    device.set_orange_led(1 << 7); // Enable sensor error warning
-    device.set_rgb_intensity(0);
+    let cleaning_view = View::new();
+    unsafe {
+        cleaning_view.set_context(&raw mut context as *mut _);
+        cleaning_view.set_draw_callback(Some(cleaning_draw_callback));
+        cleaning_view.set_input_callback(Some(cleaning_input_callback));
+        cleaning_view.set_previous_callback(Some(cleaning_back));
+    }
+
+    view_dispatcher.add_view(MAIN_VIEW, &view);
+    view_dispatcher.add_view(SETTINGS_VIEW, &settings_view);
+    view_dispatcher.add_view(MODES_VIEW, &modes_view);
+    view_dispatcher.add_view(DIAGNOSTICS_VIEW, &diagnostics_view);
+    view_dispatcher.add_view(CLEANING_VIEW, &cleaning_view);
+    view_dispatcher.switch_to_view(MAIN_VIEW);
 
     view_dispatcher.run();
 
+    view_dispatcher.remove_view(CLEANING_VIEW);
+    view_dispatcher.remove_view(DIAGNOSTICS_VIEW);
+    view_dispatcher.remove_view(MODES_VIEW);
+    view_dispatcher.remove_view(SETTINGS_VIEW);
     view_dispatcher.remove_view(MAIN_VIEW);
 
+    drop(receiver);
+    if let Some(serial) = &serial {
+        serial.deinit();
+    }
+
     0
 }
@@ -7,48 +7,80 @@
 // Required for panic handler
 extern crate flipperzero_rt;
 
-use core::ffi::{c_void, CStr};
+use core::ffi::{c_double, c_void, CStr};
 use core::mem::MaybeUninit;
-use core::sync::atomic::{AtomicU32, Ordering};
 use core::time::Duration;
 use core::{mem, ptr};
 
 use flipperzero::furi::sync::Mutex;
-use flipperzero::furi::time::FuriDuration;
-use flipperzero::furi::thread;
-use flipperzero::gpio::i2c;
 use flipperzero::{format, println};
 use flipperzero_rt::{entry, manifest};
 use flipperzero_sys as sys;
 use sys::furi::Status;
 
+use shared::fmt::display;
+use shared::furi::delay::Delay;
+use shared::furi::hal::i2c::I2cBus;
+use shared::furi::record::Record;
+use shared::nicla_sense_env::NiclaSenseEnv;
+use shared::humidity::HumiditySource;
+use shared::sgp30::{self, Baseline, RawSignals, Sgp30};
+use shared::sprintf;
+use shared::storage::Storage;
+
 const POLL_INTERVAL: Duration = Duration::from_secs(1);
-const SGP30_ADDR: i2c::DeviceAddress = i2c::DeviceAddress::new(0x58);
-const SGP30_IAQ_INIT: [u8; 2] = [0x20, 0x03];
-const SGP30_MEASURE_IAQ: [u8; 2] = [0x20, 0x08];
-const SGP30_SET_ABS_HUMIDITY: [u8; 2] = [0x20, 0x61];
-const SGP30_GET_INCEPTIVE_BASELINE: [u8; 2] = [0x20, 0xB3];
-const SGP30_SET_INCEPTIVE_BASELINE: [u8; 2] = [0x20, 0x77];
-const SGP30_GET_SERIAL: [u8; 2] = [0x36, 0x82];
+
+const BASELINE_DIR: &CStr = c"/ext/apps_data/sgp30";
+const BASELINE_PATH: &CStr = c"/ext/apps_data/sgp30/baseline.bin";
+/// Saved baselines older than this are not restored.
+const BASELINE_MAX_AGE_SECS: u32 = 7 * 24 * 60 * 60;
+/// How often to save the baseline.
+const BASELINE_SAVE_INTERVAL_SECS: u32 = 60 * 60;
+/// How long the sensor must run before its first baseline is valid.
+const BASELINE_INITIAL_SECS: u32 = 12 * 60 * 60;
+/// How often to update humidity compensation from the companion sensor.
+const HUMIDITY_UPDATE_INTERVAL_SECS: u32 = 10;
 
 // GUI record
 const RECORD_GUI: &CStr = c"gui";
 const FULLSCREEN: sys::GuiLayer = sys::GuiLayerFullscreen;
 
-static CURRENT_SAMPLE: AtomicU32 = AtomicU32::new(0);
 static STATE: Mutex<State> = Mutex::new(State::new());
 
+type Sensor<'a> = Sgp30<&'a mut I2cBus, Delay>;
+
+/// Screen currently displayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Page {
+    /// Air quality measurements.
+    AirQuality,
+    /// Raw signals and self-test.
+    Raw,
+}
+
 #[derive(Debug, Clone)]
 struct State {
+    page: Page,
     co2_eq: u16,
     tvoc: u16,
+    /// Absolute humidity (g/m³) from companion sensor.
+    abs_humidity: Option<f32>,
+    raw: Option<RawSignals>,
+    feature_set: Option<u16>,
+    /// Result of last self-test.
+    self_test: Option<Result<u16, sgp30::Error>>,
 }
 
 impl State {
     pub const fn new() -> Self {
         State {
+            page: Page::AirQuality,
             co2_eq: 0,
             tvoc: 0,
+            abs_humidity: None,
+            raw: None,
+            feature_set: None,
+            self_test: None,
         }
     }
 
@@ -69,11 +101,6 @@ manifest!(
 // Define the entry function
 entry!(main);
 
-/// Read u16 (big endian)
-fn read_u16_be(data: &[u8]) -> u16 {
-    (data[0] as u16) << 8 | data[1] as u16
-}
-
 /// View draw handler.
 ///
 /// # Safety
@@ -81,6 +108,11 @@ fn read_u16_be(data: &[u8]) -> u16 {
 /// This must only be called from a valid draw handler.
 pub unsafe extern "C" fn draw_callback(canvas: *mut sys::Canvas, _context: *mut c_void) {
     let state = STATE.lock();
+    if state.page == Page::Raw {
+        draw_raw(canvas, &state);
+        return;
+    }
+
     if state.is_zero() {
         let msg = format!("Warming up...");
         let width: i32 = sys::canvas_width(canvas).try_into().unwrap();
@@ -100,6 +132,39 @@ pub unsafe extern "C" fn draw_callback(canvas: *mut sys::Canvas, _context: *mut
         format!("SGP30 Gas Sensor"),
         format!("CO2eq: {} ppm", state.co2_eq),
         format!("TVOC: {} ppb", state.tvoc),
+        match state.abs_humidity {
+            Some(value) => sprintf!(c"Abs. humidity: %0.1f g/m3", value as c_double),
+            None => format!("Abs. humidity: fixed"),
+        },
+    ];
+
+    for (n, line) in lines.iter().enumerate() {
+        sys::canvas_draw_str(canvas, 0, (n + 1) as i32 * 10, line.as_c_str().as_ptr());
+    }
+}
+
+/// Draw raw signals and self-test page.
+unsafe fn draw_raw(canvas: *mut sys::Canvas, state: &State) {
+    let lines = [
+        format!("SGP30 Raw Signals"),
+        match state.raw {
+            Some(raw) => format!("H2: {} ticks", raw.h2),
+            None => format!("H2: -"),
+        },
+        match state.raw {
+            Some(raw) => format!("Ethanol: {} ticks", raw.ethanol),
+            None => format!("Ethanol: -"),
+        },
+        match state.feature_set {
+            Some(value) => format!("Feature set: 0x{:04X}", value),
+            None => format!("Feature set: -"),
+        },
+        match state.self_test {
+            None => format!("Self-test: press OK"),
+            Some(Ok(value)) if value == sgp30::SELF_TEST_OK => format!("Self-test: passed"),
+            Some(Ok(value)) => format!("Self-test: FAILED (0x{:04X})", value),
+            Some(Err(err)) => format!("Self-test: {}", display(err)),
+        },
     ];
 
     for (n, line) in lines.iter().enumerate() {
@@ -115,7 +180,7 @@ unsafe extern "C" fn app_input_callback(input_event: *mut sys::InputEvent, ctx:
 
 // Entry point
 fn main(_args: Option<&CStr>) -> i32 {
-    let mut bus = i2c::Bus::EXTERNAL.acquire();
+    let mut bus = I2cBus::external();
 
     unsafe {
         let event_queue = sys::furi_message_queue_alloc(8, mem::size_of::<sys::InputEvent>() as u32);
@@ -132,8 +197,18 @@ fn main(_args: Option<&CStr>) -> i32 {
         let gui = sys::furi_record_open(RECORD_GUI.as_ptr()) as *mut sys::Gui;
         sys::gui_add_view_port(gui, view_port, FULLSCREEN);
 
-        let device = SGP30_ADDR;
-        let mut running = init_sgp30(device, &mut bus);
+        let storage = Record::<Storage>::open();
+        let saved_baseline = load_baseline(&storage);
+
+        let mut sensor = Sgp30::with_default_addr(&mut bus, Delay);
+        let mut running = init_sgp30(&mut sensor, saved_baseline.map(|b| b.baseline));
+
+        let mut next_baseline_save = rtc_timestamp() + if saved_baseline.is_some() {
+            BASELINE_SAVE_INTERVAL_SECS
+        } else {
+            BASELINE_INITIAL_SECS
+        };
+        let mut next_humidity_update = rtc_timestamp();
 
         let mut event: MaybeUninit<sys::InputEvent> = MaybeUninit::uninit();
         while running {
@@ -145,14 +220,53 @@ fn main(_args: Option<&CStr>) -> i32 {
             .is_err()
             {
                 let event = event.assume_init();
-                if event.type_ == sys::InputTypePress && event.key == sys::InputKeyBack {
-                    running = false;
-                    continue;
+                if event.type_ == sys::InputTypePress {
+                    match event.key {
+                        sys::InputKeyBack => {
+                            running = false;
+                            continue;
+                        }
+                        sys::InputKeyLeft | sys::InputKeyRight => {
+                            let mut state = STATE.lock();
+                            state.page = match state.page {
+                                Page::AirQuality => Page::Raw,
+                                Page::Raw => Page::AirQuality,
+                            };
+                            sys::view_port_update(view_port);
+                        }
+                        sys::InputKeyOk if STATE.lock().page == Page::Raw => {
+                            run_self_test(&mut sensor);
+                            sys::view_port_update(view_port);
+                        }
+                        _ => (),
+                    }
                 }
             }
 
             // This must be called once per second for the sensor's dynamic callibration
-            read_sgp30(device, &mut bus, view_port);
+            read_sgp30(&mut sensor, view_port);
+            if STATE.lock().page == Page::Raw {
+                read_raw(&mut sensor);
+            }
+
+            let now = rtc_timestamp();
+            if now >= next_humidity_update {
+                update_humidity_compensation(&mut sensor);
+                next_humidity_update = now + HUMIDITY_UPDATE_INTERVAL_SECS;
+            }
+
+            if now >= next_baseline_save {
+                match sensor.iaq_baseline() {
+                    Ok(baseline) => {
+                        if !save_baseline(&storage, SavedBaseline { timestamp: now, baseline }) {
+                            println!("ERROR: failed to save baseline");
+                        }
+                    }
+                    Err(err) => println!("ERROR: GET_IAQ_BASELINE failed: {}", display(err)),
+                }
+
+                next_baseline_save = now + BASELINE_SAVE_INTERVAL_SECS;
+            }
         }
 
         // GUI Cleanup
@@ -165,177 +279,191 @@ fn main(_args: Option<&CStr>) -> i32 {
     0
 }
 
-fn sgp30_get_serial_id(
-    device: i2c::DeviceAddress,
-    bus: &mut i2c::BusHandle,
-) -> Result<[u8; 6], i2c::Error> {
-    let timeout = FuriDuration::from_millis(100);
-    let mut buffer = [0u8; 9];
+fn init_sgp30(sensor: &mut Sensor, baseline: Option<Baseline>) -> bool {
+    if !sensor.is_ready() {
+        println!("ERROR: device not ready");
+        return false;
+    }
 
-    bus.trx(device, &SGP30_GET_SERIAL, &mut buffer, timeout)?;
+    match sensor.serial_id() {
+        Ok(serial_id) => println!(
+            "Serial (hex): {:x} {:x} {:x} {:x} {:x} {:x}",
+            serial_id[0], serial_id[1], serial_id[2], serial_id[3], serial_id[4], serial_id[5]
+        ),
+        Err(err) => {
+            println!("ERROR: GET_SERIAL_ID failed: {}", display(err));
+            return false;
+        }
+    }
 
-    Ok([
-        buffer[0], buffer[1], buffer[3], buffer[4], buffer[6], buffer[7],
-    ])
-}
+    if let Err(err) = sensor.iaq_init() {
+        println!("ERROR: IAQ_INIT failed: {}", display(err));
+        return false;
+    }
 
-fn sgp30_iaq_init(device: i2c::DeviceAddress, bus: &mut i2c::BusHandle) -> Result<(), i2c::Error> {
-    let timeout = FuriDuration::from_millis(100);
-    bus.tx(device, &SGP30_IAQ_INIT, timeout)?;
-    thread::sleep(Duration::from_millis(12));
+    let abs_humidity = u16::from_be_bytes([11, (8 * 0xFF / 10) as u8]); // abs humidity = 11.8 g/m³
+    if let Err(err) = sensor.set_abs_humidity(abs_humidity) {
+        println!("ERROR: SET_ABS_HUMIDITY failed: {}", display(err));
+        return false;
+    }
 
-    Ok(())
-}
+    match sensor.feature_set() {
+        Ok(feature_set) => STATE.lock().feature_set = Some(feature_set.0),
+        Err(err) => println!("ERROR: GET_FEATURE_SET failed: {}", display(err)),
+    }
 
-fn sgp30_set_abs_humidity(
-    device: i2c::DeviceAddress,
-    bus: &mut i2c::BusHandle,
-    value: &[u8; 2],
-) -> Result<(), i2c::Error> {
-    let timeout = FuriDuration::from_millis(100);
-    let crc = maxim_crc8(value, 0xFF);
-
-    let cmd = [
-        SGP30_SET_ABS_HUMIDITY[0],
-        SGP30_SET_ABS_HUMIDITY[1],
-        value[0],
-        value[1],
-        crc,
-    ];
-    bus.tx(device, &cmd, timeout)?;
-    thread::sleep(Duration::from_millis(12));
+    if let Some(baseline) = baseline {
+        println!("Restoring baseline: CO₂eq: {:#x}; TVOC: {:#x}", baseline.co2_eq, baseline.tvoc);
+        if let Err(err) = sensor.set_iaq_baseline(baseline) {
+            println!("ERROR: SET_IAQ_BASELINE failed: {}", display(err));
+            return false;
+        }
 
-    Ok(())
-}
+        return true;
+    }
+
+    let tvoc_baseline = match sensor.tvoc_inceptive_baseline() {
+        Ok(baseline) => baseline,
+        Err(err) => {
+            println!("ERROR: GET_TVOC_INCEPTIVE_BASELINE failed: {}", display(err));
+            return false;
+        }
+    };
 
-fn sgp30_get_tvoc_inceptive_baseline(
-    device: i2c::DeviceAddress,
-    bus: &mut i2c::BusHandle,
-) -> Result<[u8; 2], i2c::Error> {
-    let timeout = FuriDuration::from_millis(100);
+    if let Err(err) = sensor.set_tvoc_baseline(tvoc_baseline) {
+        println!("ERROR: SET_TVOC_BASELINE failed: {}", display(err));
+        return false;
+    }
 
-    bus.tx(device, &SGP30_GET_INCEPTIVE_BASELINE, timeout)?;
-    thread::sleep(Duration::from_millis(10));
+    true
+}
 
-    let mut buffer = [0u8; 3];
-    bus.rx(device, &mut buffer, timeout)?;
+fn read_sgp30(sensor: &mut Sensor, view_port: *mut sys::ViewPort) {
+    let measurement = match sensor.measure_iaq() {
+        Ok(measurement) => measurement,
+        Err(sgp30::Error::NotReady) => return,
+        Err(err) => {
+            println!("ERROR: MEASURE_IAQ failed: {}", display(err));
+            return;
+        }
+    };
 
-    let cmd = [
-        SGP30_SET_INCEPTIVE_BASELINE[0],
-        SGP30_SET_INCEPTIVE_BASELINE[1],
-        buffer[0],
-        buffer[1],
-        buffer[2],
-    ];
-    bus.tx(device, &cmd, timeout)?;
-    thread::sleep(Duration::from_millis(10));
+    let mut state = STATE.lock();
+    state.co2_eq = measurement.co2_eq;
+    state.tvoc = measurement.tvoc;
 
-    Ok([buffer[0], buffer[1]])
+    println!("CO₂eq: {} ppm; TVOC: {} (ppb)", measurement.co2_eq, measurement.tvoc);
+    unsafe { sys::view_port_update(view_port) };
 }
 
-fn sgp30_set_tvoc_baseline(
-    device: i2c::DeviceAddress,
-    bus: &mut i2c::BusHandle,
-    value: &[u8; 2],
-) -> Result<(), i2c::Error> {
-    let timeout = FuriDuration::from_millis(100);
-    let crc = maxim_crc8(value, 0xFF);
-
-    let cmd = [
-        SGP30_SET_INCEPTIVE_BASELINE[0],
-        SGP30_SET_INCEPTIVE_BASELINE[1],
-        value[0],
-        value[1],
-        crc,
-    ];
-    bus.tx(device, &cmd, timeout)?;
-    thread::sleep(Duration::from_millis(10));
-
-    Ok(())
+fn read_raw(sensor: &mut Sensor) {
+    match sensor.measure_raw() {
+        Ok(raw) => STATE.lock().raw = Some(raw),
+        Err(err) => println!("ERROR: MEASURE_RAW failed: {}", display(err)),
+    }
 }
 
-fn sgp30_measure_iaq(
-    device: i2c::DeviceAddress,
-    bus: &mut i2c::BusHandle,
-) -> Result<(u16, u16), i2c::Error> {
-    let timeout = FuriDuration::from_millis(100);
+/// Run the on-chip self-test.
+///
+/// The self-test interrupts the air quality measurement,
+/// so it is restarted afterwards using the current baseline.
+fn run_self_test(sensor: &mut Sensor) {
+    let baseline = sensor.iaq_baseline().ok();
+
+    let result = sensor.measure_test();
+    STATE.lock().self_test = Some(result);
 
-    bus.tx(device, &SGP30_MEASURE_IAQ, timeout)?;
-    thread::sleep(Duration::from_millis(12));
+    if let Err(err) = sensor.iaq_init() {
+        println!("ERROR: IAQ_INIT failed: {}", display(err));
+        return;
+    }
 
-    let mut buffer = [0u8; 6];
-    bus.rx(device, &mut buffer, timeout)?;
+    if let Some(baseline) = baseline {
+        if let Err(err) = sensor.set_iaq_baseline(baseline) {
+            println!("ERROR: SET_IAQ_BASELINE failed: {}", display(err));
+        }
+    }
+}
 
-    let co2_eq = read_u16_be(&buffer[0..2]);
-    let tvoc = read_u16_be(&buffer[3..5]);
+/// Update humidity compensation from a Nicla Sense Env on the same bus.
+///
+/// The fixed value set by `init_sgp30` remains in use if no reading is available.
+fn update_humidity_compensation(sensor: &mut Sensor) {
+    let Some((temperature, humidity)) = NiclaSenseEnv::with_default_addr(sensor.bus(), Delay).temperature_humidity() else {
+        return;
+    };
 
-    Ok((co2_eq, tvoc))
+    match sensor.set_humidity_compensation(temperature, humidity) {
+        Ok(abs_humidity) => STATE.lock().abs_humidity = Some(abs_humidity),
+        Err(err) => println!("ERROR: SET_ABS_HUMIDITY failed: {}", display(err)),
+    }
 }
 
-fn maxim_crc8(data: &[u8], crc_init: u8) -> u8 {
-    let data_size = data
-        .len()
-        .try_into()
-        .expect("maxim_crc8 data buffer too large");
+/// Current RTC time (seconds since UNIX epoch).
+fn rtc_timestamp() -> u32 {
+    unsafe { sys::furi_hal_rtc_get_timestamp() }
+}
 
-    unsafe { sys::maxim_crc8(data.as_ptr(), data_size, crc_init) }
+/// IAQ baseline saved to storage.
+#[derive(Debug, Clone, Copy)]
+struct SavedBaseline {
+    /// RTC timestamp at which the baseline was read.
+    timestamp: u32,
+    baseline: Baseline,
 }
 
-fn init_sgp30(device: i2c::DeviceAddress, bus: &mut i2c::BusHandle) -> bool {
-    let timeout = FuriDuration::from_millis(100);
-    if !bus.is_device_ready(device, timeout) {
-        println!("ERROR: device not ready");
-        return false;
-    }
+impl SavedBaseline {
+    const SIZE: usize = 8;
 
-    if let Ok(serial_id) = sgp30_get_serial_id(device, bus) {
-        println!(
-            "Serial (hex): {:x} {:x} {:x} {:x} {:x} {:x}",
-            serial_id[0], serial_id[1], serial_id[2], serial_id[3], serial_id[4], serial_id[5]
-        );
-    } else {
-        println!("ERROR: GET_SERIAL_ID failed");
-        return false;
-    };
+    fn to_bytes(self) -> [u8; Self::SIZE] {
+        let [t0, t1, t2, t3] = self.timestamp.to_le_bytes();
+        let [c0, c1] = self.baseline.co2_eq.to_le_bytes();
+        let [v0, v1] = self.baseline.tvoc.to_le_bytes();
 
-    if sgp30_iaq_init(device, bus).is_err() {
-        println!("ERROR: IAQ_INIT failed");
-        return false;
+        [t0, t1, t2, t3, c0, c1, v0, v1]
     }
 
-    let abs_humidity: [u8; 2] = [11, (8 * 0xFF / 10) as u8]; // abs humidity = 11.8 g/m³
-    if sgp30_set_abs_humidity(device, bus, &abs_humidity).is_err() {
-        println!("ERROR: SET_ABS_HUMIDITY failed");
-        return false;
+    fn from_bytes(buf: &[u8; Self::SIZE]) -> Self {
+        SavedBaseline {
+            timestamp: u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]),
+            baseline: Baseline {
+                co2_eq: u16::from_le_bytes([buf[4], buf[5]]),
+                tvoc: u16::from_le_bytes([buf[6], buf[7]]),
+            },
+        }
     }
+}
 
-    let Ok(tvoc_baseline) = sgp30_get_tvoc_inceptive_baseline(device, bus) else {
-        println!("ERROR: GET_TVOC_INCEPTIVE_BASELINE failed");
-        return false;
-    };
+/// Load the saved baseline, if it is recent enough to be restored.
+fn load_baseline(storage: &Record<Storage>) -> Option<SavedBaseline> {
+    let mut file = storage.open_file(BASELINE_PATH, sys::FSAM_READ, sys::FSOM_OPEN_EXISTING)?;
 
-    if sgp30_set_tvoc_baseline(device, bus, &tvoc_baseline).is_err() {
-        println!("ERROR: SET_TVOC_BASELINE failed");
-        return false;
+    let mut buf = [0u8; SavedBaseline::SIZE];
+    if file.read(&mut buf) != buf.len() {
+        println!("ERROR: saved baseline is truncated");
+        return None;
     }
 
-    true
-}
-
-fn read_sgp30(device: i2c::DeviceAddress, bus: &mut i2c::BusHandle, view_port: *mut sys::ViewPort) {
-    let Ok((co2_eq, tvoc)) = sgp30_measure_iaq(device, bus) else {
-        println!("ERROR: MEASURE_IAQ failed");
-        return;
-    };
+    let saved = SavedBaseline::from_bytes(&buf);
+    let age = rtc_timestamp().saturating_sub(saved.timestamp);
+    if age > BASELINE_MAX_AGE_SECS {
+        println!("Saved baseline is too old ({} s)", age);
+        return None;
+    }
 
-    if CURRENT_SAMPLE.load(Ordering::SeqCst) > 15 {
-        let mut state = STATE.lock();
-        state.co2_eq = co2_eq;
-        state.tvoc = tvoc;
+    Some(saved)
+}
 
-        println!("CO₂eq: {} ppm; TVOC: {} (ppb)", co2_eq, tvoc);
-        unsafe { sys::view_port_update(view_port) };
+/// Save baseline to storage.
+fn save_baseline(storage: &Record<Storage>, saved: SavedBaseline) -> bool {
+    if !storage.simply_mkdir(BASELINE_DIR) {
+        return false;
     }
 
-    CURRENT_SAMPLE.fetch_add(1, Ordering::SeqCst);
+    let Some(mut file) = storage.open_file(BASELINE_PATH, sys::FSAM_WRITE, sys::FSOM_CREATE_ALWAYS) else {
+        return false;
+    };
+
+    let data = saved.to_bytes();
+    file.write(&data) == data.len()
 }
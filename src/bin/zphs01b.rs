@@ -8,7 +8,6 @@
 extern crate flipperzero_rt;
 
 use core::ffi::{c_void, CStr};
-use core::ops::Not;
 use core::sync::atomic::{AtomicU32, Ordering};
 use core::time::Duration;
 use core::ptr;
@@ -22,15 +21,18 @@ use flipperzero_rt::{entry, manifest};
 use flipperzero_sys as sys;
 
 use shared::sprintf;
+use shared::fmt::display;
+use shared::furi::hal::power::OtgGuard;
 use shared::furi::hal::serial::SerialHandle;
+use shared::transaction::Client;
+use shared::voc::VOCLevel;
+use shared::zphs01b::{AirQualityIndex, Decoder, Measurement, CMD_FETCH};
 
 const POLL_INTERVAL: Duration = Duration::from_millis(500);
 const CHANNEL: sys::FuriHalSerialId = sys::FuriHalSerialIdLpuart;
 const BAUD: u32 = 9600;
-
-const CMD_FETCH: [u8; 9] = [0xFF, 0x01, 0x86, 0x00, 0x00, 0x00, 0x00, 0x00, 0x79];
-const RESPONSE_SIZE: usize = 26;
-const START_BIT: u8 = 0xFF;
+/// Time to wait for each byte of a response.
+const RESPONSE_TIMEOUT_MS: u64 = 200;
 
 // GUI record
 const RECORD_GUI: &CStr = c"gui";
@@ -38,6 +40,8 @@ const FULLSCREEN: sys::GuiLayer = sys::GuiLayerFullscreen;
 
 static SAMPLE_COUNT: AtomicU32 = AtomicU32::new(0);
 static VALUES: Mutex<Measurement> = Mutex::new(Measurement::new());
+/// Shown instead of the title if the module may not be powered.
+static POWER_WARNING: Mutex<Option<&'static str>> = Mutex::new(None);
 
 // Define the FAP Manifest for this application
 manifest!(
@@ -51,17 +55,6 @@ manifest!(
 // Define the entry function
 entry!(main);
 
-fn read_u16_be(data: &[u8]) -> u16 {
-    (data[0] as u16) << 8 | data[1] as u16
-}
-
-fn calculate_checksum(data: &[u8]) -> u8 {
-    data.iter()
-        .fold(0u8, |acc, &x| acc.wrapping_add(x))
-        .not()
-        .wrapping_add(1)
-}
-
 /// View draw handler.
 /// Screen is 128x64 px
 unsafe extern "C" fn draw_callback(canvas: *mut sys::Canvas, _context: *mut c_void) {
@@ -74,8 +67,12 @@ unsafe extern "C" fn draw_callback(canvas: *mut sys::Canvas, _context: *mut c_vo
         VOCLevel::Moderate => c"moderate",
         VOCLevel::Severe => c"severe",
     };
+    let title = match *POWER_WARNING.lock() {
+        Some(warning) => format!("{}", warning),
+        None => format!("Winsen ZPHS01B Gas Sensor"),
+    };
     let lines = [
-        format!("Winsen ZPHS01B Gas Sensor"),
+        title,
         sprintf!(
             c"PM (1, 2.5, 10): (%u, %u, %u) ugm3",
             pm_1 as u32,
@@ -142,91 +139,17 @@ fn main(_args: Option<&CStr>) -> i32 {
         sys::gui_add_view_port(gui.as_ptr(), view_port, FULLSCREEN);
     }
 
+    // Module needs 5V
+    let otg = OtgGuard::enable();
+    update_power_warning(otg.as_ref());
+
     // UART setup
     let serial_handle = SerialHandle::acquire(CHANNEL).unwrap();
     serial_handle.init(BAUD);
 
-    let mut buffer: heapless::Vec<u8, RESPONSE_SIZE> = heapless::Vec::new();
-    let mut serial = serial_handle.async_receiver(move |data| {
-        if buffer.is_empty() && data[0] != START_BIT {
-            return;
-        }
-
-        let to_read = data.len().min(buffer.capacity() - buffer.len());
-        buffer.extend_from_slice(&data[..to_read]).unwrap();
-
-        if buffer.len() < RESPONSE_SIZE {
-            return;
-        }
-
-        let mut values = VALUES.lock();
-        let last_aqi = values.air_quality_index();
-
-        match Measurement::try_from(&buffer[..]) {
-            Err(_) => {
-                buffer.clear();
-                return;
-            },
-            Ok(v) => {
-                *values = v;
-                buffer.clear();
-            }
-        }
-
-        println!("PM 1: {} μg/m³", values.pm_1);
-        println!("PM 2.5: {} μg/m³", values.pm_2_5);
-        println!("PM 10: {} μg/m³", values.pm_10);
-        println!("CO₂: {} ppm (ideally below 1000 ppm)", values.co2);
-        println!("VOC: {} (0 to 3)", values.voc);
-        println!("Temperature: {}.{} °C", values.temp / 10 - 50, values.temp % 10);
-        println!("Humidity: {} %", values.relative_humidity);
-        println!(
-            "Formaldehyde (CH₂O): {}.{}{}{} mg/m³ (ideally below 0.1 mg/m³)",
-            values.ch2o / 1000,
-            (values.ch2o / 100) % 10,
-            (values.ch2o / 10) % 10,
-            values.ch2o % 10
-        );
-        println!(
-            "Carbon Monoxide (CO): {}.{} ppm (ideally below 9 ppm)",
-            values.co / 10,
-            values.co % 10
-        );
-        println!(
-            "Ozone (O₃): {}.{}{} ppm (ideally below 0.08 ppm)",
-            values.o3 / 100,
-            (values.o3 / 10) % 10,
-            values.o3 % 10
-        );
-        println!(
-            "Nitrogen Dioxide (NO₂): {}.{}{} ppm (ideally below 0.05 ppm)",
-            values.no2 / 100,
-            (values.no2 / 10) % 10,
-            values.no2 % 10
-        );
-        println!("");
-
-        let aqi = values.air_quality_index();
-        if aqi != last_aqi {
-            notification_service.notify(match aqi {
-                AirQualityIndex::Good => &NOTIFICATION_GOOD,
-                AirQualityIndex::Moderate => &NOTIFICATION_MODERATE,
-                AirQualityIndex::Sensitive => &NOTIFICATION_SENSITIVE,
-                AirQualityIndex::Unhealthy => &NOTIFICATION_UNHEALTHY,
-                AirQualityIndex::VeryUnhealthy => &NOTIFICATION_VERY_UNHEALTHY,
-                AirQualityIndex::Hazardous => &NOTIFICATION_HAZARDOUS,
-            });
-        }
-
-        SAMPLE_COUNT.fetch_add(1, Ordering::AcqRel);
-
-        unsafe {
-            sys::view_port_update(view_port);
-        }
-    });
-
-    println!("Starting serial reader...");
-    serial.start();
+    let mut reader = serial_handle.reader();
+    reader.set_timeout_ms(RESPONSE_TIMEOUT_MS);
+    let mut client = Client::new(reader, Decoder::new());
 
     loop {
         match event_queue.get(POLL_INTERVAL.try_into().unwrap()) {
@@ -241,11 +164,16 @@ fn main(_args: Option<&CStr>) -> i32 {
             },
         }
 
+        update_power_warning(otg.as_ref());
+
         println!("Sending FETCH...");
-        serial_handle.tx(&CMD_FETCH);
+        match client.request(&CMD_FETCH) {
+            Ok(measurement) => on_measurement(measurement, &mut notification_service, view_port),
+            Err(err) => error!("FETCH failed: {}", display(err)),
+        }
     }
 
-    serial.stop();
+    drop(client);
 
     // GUI Cleanup
     unsafe {
@@ -257,186 +185,80 @@ fn main(_args: Option<&CStr>) -> i32 {
     0
 }
 
-#[derive(Debug, Default)]
-struct Measurement {
-    pm_1: u16,
-    pm_2_5: u16,
-    pm_10: u16,
-    co2: u16,
-    voc: u8,
-    temp: u16,
-    relative_humidity: u16,
-    ch2o: u16,
-    co: u16,
-    o3: u16,
-    no2: u16,
-}
-
-impl Measurement {
-    const fn new() -> Self {
-        Measurement {
-            pm_1: 0,
-            pm_2_5: 0,
-            pm_10: 0,
-            co2: 0,
-            voc: 0,
-            temp: 0,
-            relative_humidity: 0,
-            ch2o: 0,
-            co: 0,
-            o3: 0,
-            no2: 0,
-        }
-    }
-
-    /// Overall air quality.
-    /// Based on the EPA AQI (8 hours)
-    fn air_quality_index(&self) -> AirQualityIndex {
-        let o3 = self.o3();
-        let (_pm_1, pm_2_5, pm_10) = self.pm();
-        let co = self.co();
-        let no2 = self.no2();
-
-        // Hazardous (Maroon)
-        if o3 > 0.200 || pm_2_5 > 250 || pm_10 > 424 || co > 30.0 || no2 > 1.249 {
-            return AirQualityIndex::Hazardous;
-        }
-
-        // Very Unhealthy (Purple)
-        if o3 > 0.105 || pm_2_5 > 150 || pm_10 > 354 || co > 15.4 || no2 > 0.649 {
-            return AirQualityIndex::VeryUnhealthy;
-        }
-
-        // Unhealthy (Red)
-        if o3 > 0.085 || pm_2_5 > 55 || pm_10 > 254 || co > 12.4 || no2 > 0.360 {
-            return AirQualityIndex::Unhealthy;
-        }
-
-        // Unhealthy for Sensitive Groups (Orange)
-        if o3 > 0.070 || pm_2_5 > 35 || pm_10 > 154 || co > 9.4 || no2 > 0.100 {
-            return AirQualityIndex::Sensitive;
-        }
-
-        // Moderate (Yellow)
-        if o3 > 0.054 || pm_2_5 > 12 || pm_10 > 54 || co > 4.4 || no2 > 0.053 {
-            return AirQualityIndex::Moderate;
-        }
-
-        // Good (Green)
-        AirQualityIndex::Good
-    }
-
-    /// Particulate matter (μg/m) for PM 1, PM 2.5 and PM 10.
-    fn pm(&self) -> (u16, u16, u16) {
-        (self.pm_1, self.pm_2_5, self.pm_10)
-    }
-
-    /// Carbon Dioxide (ppm CO₂).
-    /// Ideally should be below 1000 ppm.
-    fn co2(&self) -> u16 {
-        self.co2
-    }
+/// Warn if the 5V output that powers the module is off or the battery is low.
+fn update_power_warning(otg: Option<&OtgGuard>) {
+    let warning = match otg {
+        None => Some("No 5V: battery too low?"),
+        Some(otg) if !otg.is_active() => Some("5V output switched off"),
+        Some(otg) if otg.is_battery_low() => Some("Low battery, 5V may fail"),
+        Some(_) => None,
+    };
 
-    /// Volitile Organic Compounds.
-    fn voc(&self) -> VOCLevel {
-        match self.voc {
-            0 => VOCLevel::Clean,
-            1 => VOCLevel::Light,
-            2 => VOCLevel::Moderate,
-            3 => VOCLevel::Severe,
-            _ => panic!("unexpected VOC value"),
+    let mut power_warning = POWER_WARNING.lock();
+    if warning != *power_warning {
+        if let Some(warning) = warning {
+            println!("WARNING: {}", warning);
         }
-    }
-
-    /// Temperature (°C) accurate to 1 decimal place.
-    fn temperature(&self) -> f64 {
-        0.10 * self.temp as f64 - 50.0
-    }
-
-    /// Relative Humidity (%).
-    fn relative_humidity(&self) -> u16 {
-        self.relative_humidity
-    }
-
-    /// Formaldehyde (mg/m³ CH₂O) accurate to 3 decimal places.
-    /// Ideally should be below 0.1 mg/m³.
-    fn ch2o(&self) -> f64 {
-        0.001 * self.ch2o as f64
-    }
-
-    /// Carbon Monoxide (ppm CO) accurate to 1 decimal place.
-    /// Ideally should be below 9 ppm.
-    fn co(&self) -> f64 {
-        0.1 * self.co as f64
-    }
-
-    /// Ozone (ppm O₃) accurate to 2 decimal places.
-    /// Ideally should be below 0.08 ppm.
-    fn o3(&self) -> f64 {
-        0.01 * self.o3 as f64
-    }
-
-    /// Nitrogen Dioxide (ppm NO₂) accurate to 2 decimal places.
-    fn no2(&self) -> f64 {
-        0.01 * self.no2 as f64
+        *power_warning = warning;
     }
 }
 
-impl TryFrom<&[u8]> for Measurement {
-    type Error = ();
-
-    fn try_from(buffer: &[u8]) -> Result<Self, Self::Error> {
-        if buffer.len() != RESPONSE_SIZE {
-            error!("Invalid response size");
-            return Err(());
-        }
-
-        let checksum = calculate_checksum(&buffer[1..25]);
-        if checksum != buffer[25] {
-            error!("Bad checksum! 0x{:X} != 0x{:X}", checksum, buffer[25]);
-            return Err(());
-        }
-
-        Ok(Measurement {
-            pm_1: read_u16_be(&buffer[2..4]),
-            pm_2_5: read_u16_be(&buffer[4..6]),
-            pm_10: read_u16_be(&buffer[6..8]),
-            co2: read_u16_be(&buffer[8..10]),
-            voc: buffer[10],
-            temp: read_u16_be(&buffer[11..13]),
-            relative_humidity: read_u16_be(&buffer[13..15]),
-            ch2o: read_u16_be(&buffer[15..17]),
-            co: read_u16_be(&buffer[17..19]),
-            o3: read_u16_be(&buffer[19..21]),
-            no2: read_u16_be(&buffer[21..23]),
-        })
+/// Handle a newly decoded measurement.
+fn on_measurement(measurement: Measurement, notification_service: &mut NotificationService, view_port: *mut sys::ViewPort) {
+    let mut values = VALUES.lock();
+    let last_aqi = values.air_quality_index();
+    *values = measurement;
+
+    println!("PM 1: {} μg/m³", values.pm_1);
+    println!("PM 2.5: {} μg/m³", values.pm_2_5);
+    println!("PM 10: {} μg/m³", values.pm_10);
+    println!("CO₂: {} ppm (ideally below 1000 ppm)", values.co2);
+    println!("VOC: {} (0 to 3)", values.voc);
+    println!("Temperature: {}.{} °C", values.temp / 10 - 50, values.temp % 10);
+    println!("Humidity: {} %", values.relative_humidity);
+    println!(
+        "Formaldehyde (CH₂O): {}.{}{}{} mg/m³ (ideally below 0.1 mg/m³)",
+        values.ch2o / 1000,
+        (values.ch2o / 100) % 10,
+        (values.ch2o / 10) % 10,
+        values.ch2o % 10
+    );
+    println!(
+        "Carbon Monoxide (CO): {}.{} ppm (ideally below 9 ppm)",
+        values.co / 10,
+        values.co % 10
+    );
+    println!(
+        "Ozone (O₃): {}.{}{} ppm (ideally below 0.08 ppm)",
+        values.o3 / 100,
+        (values.o3 / 10) % 10,
+        values.o3 % 10
+    );
+    println!(
+        "Nitrogen Dioxide (NO₂): {}.{}{} ppm (ideally below 0.05 ppm)",
+        values.no2 / 100,
+        (values.no2 / 10) % 10,
+        values.no2 % 10
+    );
+    println!("");
+
+    let aqi = values.air_quality_index();
+    if aqi != last_aqi {
+        notification_service.notify(match aqi {
+            AirQualityIndex::Good => &NOTIFICATION_GOOD,
+            AirQualityIndex::Moderate => &NOTIFICATION_MODERATE,
+            AirQualityIndex::Sensitive => &NOTIFICATION_SENSITIVE,
+            AirQualityIndex::Unhealthy => &NOTIFICATION_UNHEALTHY,
+            AirQualityIndex::VeryUnhealthy => &NOTIFICATION_VERY_UNHEALTHY,
+            AirQualityIndex::Hazardous => &NOTIFICATION_HAZARDOUS,
+        });
     }
-}
 
-/// VOC levels reported by ZP01-MP503.
-#[derive(Debug, Clone, Copy)]
-enum VOCLevel {
-    Clean,
-    Light,
-    Moderate,
-    Severe,
-}
+    SAMPLE_COUNT.fetch_add(1, Ordering::AcqRel);
 
-/// Air Quality Index (see https://www.airnow.gov/aqi/aqi-basics/)
-/// 0 to 50: Good (Green)
-/// 51 to 100: Moderate (Yellow)
-/// 101 to 150: Unhealthy for Sensitive Groups (Orange)
-/// 151 to 200: Unhealthy (Red)
-/// 201 to 300: Very Unhealthy (Purple)
-/// 301 to 500: Hazardous (Maroon)
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum AirQualityIndex {
-    Good,
-    Moderate,
-    Sensitive,
-    Unhealthy,
-    VeryUnhealthy,
-    Hazardous,
+    unsafe {
+        sys::view_port_update(view_port);
+    }
 }
 
 const NOTIFICATION_GOOD: NotificationSequence = notification_sequence!([
@@ -480,27 +302,3 @@ const NOTIFICATION_HAZARDOUS: NotificationSequence = notification_sequence!([
     NotificationMessage::led_blue(35),
     NotificationMessage::do_not_reset(),
 ]);
-
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    /// Test data
-    /// PM1.0 = 101 ug/m3
-    /// PM2.5 = 54 ug/m3
-    /// PM10 = 150 ug/m3
-    /// CO2 = 410 ppm
-    /// VOC = 0
-    /// Temp = 25.5 degC
-    /// Humidity = 40% RH
-    /// CH2O = 0.040 mg/m3
-    /// O3 = 0.32 ppm
-    /// NO2 = 0.80 ppm
-    const TEST_DATA: [u8; RESPONSE_SIZE] = [0xFF, 0x86, 0x00, 0x65, 0x00, 0x36, 0x00, 0x96, 0x01, 0x9A, 0x00, 0x02, 0xFD, 0x00, 0x28, 0x00, 0x28, 0x00, 0x05, 0x00, 0x20, 0x00, 0x50, 0x00, 0x00, 0xEA];
-
-    #[test]
-    fn test_measurement_decode() {
-
-    }
-}
@@ -0,0 +1,33 @@
+//! Checksum algorithms used by sensor protocols.
+
+/// Sensirion CRC-8 (polynomial `0x31`, init `0xFF`, no reflection, no final XOR).
+///
+/// Used to protect each 16-bit word sent to or received from Sensirion sensors.
+pub fn sensirion_crc8(data: &[u8]) -> u8 {
+    const POLYNOMIAL: u8 = 0x31;
+
+    let mut crc = 0xFF;
+    for &byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ POLYNOMIAL
+            } else {
+                crc << 1
+            };
+        }
+    }
+
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sensirion_crc8() {
+        // Example from SGP30 datasheet
+        assert_eq!(sensirion_crc8(&[0xBE, 0xEF]), 0x92);
+    }
+}
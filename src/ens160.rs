@@ -0,0 +1,395 @@
+//! Driver for the [ScioSense ENS160](https://www.sciosense.com/ens160-digital-metal-oxide-multi-gas-sensor/)
+//! digital metal-oxide multi-gas sensor.
+
+use embedded_hal::i2c::I2c;
+
+/// Operating mode of the sensor.
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum OperatingMode {
+    /// Low-power standby mode.
+    #[default]
+    DeepSleep = 0x00,
+    /// Low power mode that allows commands to be issued.
+    Idle = 0x01,
+    /// Gas sensing mode.
+    Standard = 0x02,
+    /// Reset the device.
+    Reset = 0xF0,
+}
+
+impl TryFrom<u8> for OperatingMode {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        use OperatingMode::*;
+        match value {
+            0x00 => Ok(DeepSleep),
+            0x01 => Ok(Idle),
+            0x02 => Ok(Standard),
+            0xF0 => Ok(Reset),
+            _ => Err(value),
+        }
+    }
+}
+
+/// Air Quality Index according to the UBA (German Federal Environmental Agency).
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AirQualityIndex {
+    Excellent = 1,
+    Good = 2,
+    Moderate = 3,
+    Poor = 4,
+    Unhealthy = 5,
+}
+
+impl TryFrom<u8> for AirQualityIndex {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        use AirQualityIndex::*;
+        match value {
+            1 => Ok(Excellent),
+            2 => Ok(Good),
+            3 => Ok(Moderate),
+            4 => Ok(Poor),
+            5 => Ok(Unhealthy),
+            _ => Err(value),
+        }
+    }
+}
+
+/// Validity of the sensor output.
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Validity {
+    /// Normal operation.
+    Normal = 0,
+    /// Warm-up phase (first 3 minutes after power-on).
+    WarmUp = 1,
+    /// Initial start-up phase (first full hour of operation after initial power-on).
+    InitialStartUp = 2,
+    /// Invalid output.
+    Invalid = 3,
+}
+
+/// Device Status Register.
+/// - bit 0: New data in GPR_READ registers
+/// - bit 1: New data in DATA_x registers
+/// - bit 2..3: Validity flag
+/// - bit 6: Error detected (e.g. invalid operating mode)
+/// - bit 7: Operating mode is running
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DataStatus(pub u8);
+
+impl DataStatus {
+    /// An operating mode is running.
+    pub fn is_running(self) -> bool {
+        self.0 & (1 << 7) != 0
+    }
+
+    /// An error has been detected (e.g. an invalid operating mode has been selected).
+    pub fn is_error(self) -> bool {
+        self.0 & (1 << 6) != 0
+    }
+
+    /// Validity of the current sensor output.
+    pub fn validity(self) -> Validity {
+        match (self.0 >> 2) & 3 {
+            0 => Validity::Normal,
+            1 => Validity::WarmUp,
+            2 => Validity::InitialStartUp,
+            _ => Validity::Invalid,
+        }
+    }
+
+    /// New data is available in the DATA_x registers.
+    pub fn has_new_data(self) -> bool {
+        self.0 & (1 << 1) != 0
+    }
+
+    /// New data is available in the GPR_READ registers.
+    pub fn has_new_gpr(self) -> bool {
+        self.0 & 1 != 0
+    }
+}
+
+/// Device address when `ADDR` pin is pulled low.
+pub const DEFAULT_DEVICE_ADDRESS: u8 = 0x52;
+/// Device address when `ADDR` pin is pulled high.
+pub const ALTERNATE_DEVICE_ADDRESS: u8 = 0x53;
+
+/// Expected value of the part ID register.
+pub const PART_ID: u16 = 0x0160;
+
+pub struct Ens160<I2C> {
+    i2c: I2C,
+    address: u8,
+}
+
+impl<I2C: I2c> Ens160<I2C> {
+    /// Device Identity (u16)
+    const PART_ID_REGISTER: u8 = 0x00;
+    /// Operating Mode
+    const OPMODE_REGISTER: u8 = 0x10;
+    /// Additional System Commands
+    const COMMAND_REGISTER: u8 = 0x12;
+    /// Host Ambient Temperature Information (u16, Kelvin * 64),
+    /// followed by Host Relative Humidity Information (u16, %RH * 512)
+    const TEMP_IN_REGISTER: u8 = 0x13;
+    /// Device Status
+    const DEVICE_STATUS_REGISTER: u8 = 0x20;
+    /// Air Quality Index (bits 0..3)
+    const DATA_AQI_REGISTER: u8 = 0x21;
+    /// TVOC Concentration (u16, ppb)
+    const DATA_TVOC_REGISTER: u8 = 0x22;
+    /// Equivalent CO₂ Concentration (u16, ppm)
+    const DATA_ECO2_REGISTER: u8 = 0x24;
+    /// Temperature used in calculations (u16, Kelvin * 64),
+    /// followed by Relative Humidity used in calculations (u16, %RH * 512)
+    const DATA_T_REGISTER: u8 = 0x30;
+    /// General Purpose Read Registers ([u8; 8])
+    const GPR_READ_REGISTER: u8 = 0x48;
+
+    /// No operation.
+    const COMMAND_NOP: u8 = 0x00;
+    /// Get firmware version (result is written to GPR_READ 4..6).
+    const COMMAND_GET_APPVER: u8 = 0x0E;
+    /// Clear GPR_READ registers.
+    const COMMAND_CLRGPR: u8 = 0xCC;
+
+    pub fn new(i2c: I2C, address: u8) -> Self {
+        Ens160 { i2c, address }
+    }
+
+    pub fn with_default_addr(i2c: I2C) -> Self {
+        Self::new(i2c, DEFAULT_DEVICE_ADDRESS)
+    }
+
+    /// Release the underlying bus.
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+
+    pub fn is_ready(&mut self) -> bool {
+        self.i2c.write(self.address, &[]).is_ok()
+    }
+
+    /// Get the device part number (should be [`PART_ID`]).
+    pub fn part_id(&mut self) -> Result<u16, I2C::Error> {
+        self.read_u16(Self::PART_ID_REGISTER)
+    }
+
+    /// Check that the device identifies as an ENS160.
+    pub fn check_part_id(&mut self) -> Result<bool, I2C::Error> {
+        Ok(self.part_id()? == PART_ID)
+    }
+
+    /// Get the firmware version (major, minor, release).
+    ///
+    /// Device must be in [`OperatingMode::Idle`].
+    pub fn firmware_version(&mut self) -> Result<(u8, u8, u8), I2C::Error> {
+        self.write_u8(Self::COMMAND_REGISTER, Self::COMMAND_NOP)?;
+        self.write_u8(Self::COMMAND_REGISTER, Self::COMMAND_CLRGPR)?;
+        self.write_u8(Self::COMMAND_REGISTER, Self::COMMAND_GET_APPVER)?;
+
+        let mut buf = [0u8; 3];
+        self.read_exact(Self::GPR_READ_REGISTER + 4, &mut buf)?;
+
+        Ok((buf[0], buf[1], buf[2]))
+    }
+
+    /// Get the current operating mode.
+    ///
+    /// Returns `None` if the mode is not recognised.
+    pub fn operating_mode(&mut self) -> Result<Option<OperatingMode>, I2C::Error> {
+        Ok(OperatingMode::try_from(self.read_u8(Self::OPMODE_REGISTER)?).ok())
+    }
+
+    /// Set the operating mode.
+    pub fn set_operating_mode(&mut self, mode: OperatingMode) -> Result<(), I2C::Error> {
+        self.write_u8(Self::OPMODE_REGISTER, mode as u8)
+    }
+
+    /// Put the sensor into low-power standby.
+    pub fn deep_sleep(&mut self) -> Result<(), I2C::Error> {
+        self.set_operating_mode(OperatingMode::DeepSleep)
+    }
+
+    /// Put the sensor into idle mode.
+    pub fn idle(&mut self) -> Result<(), I2C::Error> {
+        self.set_operating_mode(OperatingMode::Idle)
+    }
+
+    /// Start gas sensing.
+    pub fn start(&mut self) -> Result<(), I2C::Error> {
+        self.set_operating_mode(OperatingMode::Standard)
+    }
+
+    /// Reset the device.
+    pub fn reset(&mut self) -> Result<(), I2C::Error> {
+        self.set_operating_mode(OperatingMode::Reset)
+    }
+
+    /// Get the device status.
+    pub fn status(&mut self) -> Result<DataStatus, I2C::Error> {
+        Ok(DataStatus(self.read_u8(Self::DEVICE_STATUS_REGISTER)?))
+    }
+
+    /// Get the Air Quality Index according to the UBA (1 to 5).
+    ///
+    /// Returns `None` if no index is available yet.
+    pub fn air_quality_index(&mut self) -> Result<Option<AirQualityIndex>, I2C::Error> {
+        let aqi = self.read_u8(Self::DATA_AQI_REGISTER)? & 0x07;
+
+        Ok(AirQualityIndex::try_from(aqi).ok())
+    }
+
+    /// Get the total volatile organic compounds concentration in ppb.
+    pub fn total_voc(&mut self) -> Result<u16, I2C::Error> {
+        self.read_u16(Self::DATA_TVOC_REGISTER)
+    }
+
+    /// Get the equivalent Carbon Dioxide (CO₂) concentration in ppm (400 to 65000).
+    pub fn estimated_co2(&mut self) -> Result<u16, I2C::Error> {
+        self.read_u16(Self::DATA_ECO2_REGISTER)
+    }
+
+    /// Set the ambient temperature (degC) and relative humidity (%RH) used for compensation.
+    pub fn set_compensation(&mut self, temperature: f32, humidity: f32) -> Result<(), I2C::Error> {
+        let temp = ((temperature + 273.15) * 64.0) as u16;
+        let rh = (humidity * 512.0) as u16;
+
+        let [t0, t1] = temp.to_le_bytes();
+        let [h0, h1] = rh.to_le_bytes();
+
+        self.write_exact(Self::TEMP_IN_REGISTER, &[t0, t1, h0, h1])
+    }
+
+    /// Get the temperature (degC) and relative humidity (%RH) used for compensation.
+    pub fn compensation(&mut self) -> Result<(f32, f32), I2C::Error> {
+        let mut buf = [0u8; 4];
+        self.read_exact(Self::DATA_T_REGISTER, &mut buf)?;
+
+        let temp = u16::from_le_bytes([buf[0], buf[1]]) as f32 / 64.0 - 273.15;
+        let rh = u16::from_le_bytes([buf[2], buf[3]]) as f32 / 512.0;
+
+        Ok((temp, rh))
+    }
+
+    /// Raw resistance (Ohm) of the four hotplates.
+    ///
+    /// Only valid when [`DataStatus::has_new_gpr`] is set.
+    pub fn raw_resistance(&mut self) -> Result<[f32; 4], I2C::Error> {
+        let mut buf = [0u8; 8];
+        self.read_exact(Self::GPR_READ_REGISTER, &mut buf)?;
+
+        let (words, _) = buf.as_chunks::<2>();
+
+        Ok(core::array::from_fn(|n| raw_to_resistance(u16::from_le_bytes(words[n]))))
+    }
+
+    fn write_u8(&mut self, reg_addr: u8, data: u8) -> Result<(), I2C::Error> {
+        self.i2c.write(self.address, &[reg_addr, data])
+    }
+
+    fn write_exact(&mut self, reg_addr: u8, data: &[u8; 4]) -> Result<(), I2C::Error> {
+        let buf = [reg_addr, data[0], data[1], data[2], data[3]];
+
+        self.i2c.write(self.address, &buf)
+    }
+
+    fn read_u8(&mut self, reg_addr: u8) -> Result<u8, I2C::Error> {
+        let mut buf = [0u8; 1];
+        self.read_exact(reg_addr, &mut buf)?;
+
+        Ok(buf[0])
+    }
+
+    fn read_u16(&mut self, reg_addr: u8) -> Result<u16, I2C::Error> {
+        let mut buf = [0u8; 2];
+        self.read_exact(reg_addr, &mut buf)?;
+
+        Ok(u16::from_le_bytes(buf))
+    }
+
+    fn read_exact(&mut self, reg_addr: u8, buf: &mut [u8]) -> Result<(), I2C::Error> {
+        self.i2c.write_read(self.address, &[reg_addr], buf)
+    }
+}
+
+/// Convert raw GPR_READ value to resistance (Ohm).
+///
+/// R = 2^(value / 2048)
+fn raw_to_resistance(value: u16) -> f32 {
+    libm::exp2f(value as f32 / 2048.0)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction};
+    use std::vec;
+
+    use super::*;
+
+    #[test]
+    fn test_check_part_id() {
+        let expectations = [
+            Transaction::write_read(0x52, vec![0x00], vec![0x60, 0x01]),
+            Transaction::write_read(0x52, vec![0x00], vec![0x61, 0x01]),
+        ];
+        let mut sensor = Ens160::with_default_addr(I2cMock::new(&expectations));
+
+        assert_eq!(sensor.check_part_id(), Ok(true));
+        assert_eq!(sensor.check_part_id(), Ok(false));
+
+        sensor.release().done();
+    }
+
+    #[test]
+    fn test_status() {
+        let expectations = [Transaction::write_read(0x52, vec![0x20], vec![0x86])];
+        let mut sensor = Ens160::with_default_addr(I2cMock::new(&expectations));
+
+        let status = sensor.status().unwrap();
+        assert!(status.is_running());
+        assert!(!status.is_error());
+        assert_eq!(status.validity(), Validity::WarmUp);
+        assert!(status.has_new_data());
+        assert!(!status.has_new_gpr());
+
+        assert_eq!(DataStatus(0x0C).validity(), Validity::Invalid);
+        assert_eq!(DataStatus(0x41).validity(), Validity::Normal);
+
+        sensor.release().done();
+    }
+
+    #[test]
+    fn test_set_compensation() {
+        // 298.15 K * 64 = 0x4A89, 50 %RH * 512 = 0x6400
+        let expectations = [Transaction::write(0x52, vec![0x13, 0x89, 0x4A, 0x00, 0x64])];
+        let mut sensor = Ens160::with_default_addr(I2cMock::new(&expectations));
+
+        assert_eq!(sensor.set_compensation(25.0, 50.0), Ok(()));
+
+        sensor.release().done();
+    }
+
+    #[test]
+    fn test_raw_resistance() {
+        // 2^(value / 2048) for 0, 2048, 10 * 2048 and 20 * 2048
+        let expectations = [Transaction::write_read(
+            0x52,
+            vec![0x48],
+            vec![0x00, 0x00, 0x00, 0x08, 0x00, 0x50, 0x00, 0xA0],
+        )];
+        let mut sensor = Ens160::with_default_addr(I2cMock::new(&expectations));
+
+        assert_eq!(sensor.raw_resistance(), Ok([1.0, 2.0, 1024.0, 1048576.0]));
+
+        sensor.release().done();
+    }
+}
@@ -0,0 +1,17 @@
+//! Formatting helpers.
+//!
+//! [`flipperzero::format!`] and [`flipperzero::println!`] are based on `ufmt`,
+//! so they can't format types that only implement [`core::fmt::Display`] (e.g. error types).
+
+use core::fmt::{self, Write};
+
+use flipperzero::furi::string::FuriString;
+
+/// Format `value` with [`core::fmt::Display`], for passing to `format!` or `println!`.
+pub fn display(value: impl fmt::Display) -> FuriString {
+    let mut s = FuriString::new();
+    // Writing to a `FuriString` can't fail
+    let _ = write!(s, "{value}");
+
+    s
+}
@@ -1,3 +1,4 @@
+pub mod delay;
 pub mod hal;
 pub mod pubsub;
 pub mod record;
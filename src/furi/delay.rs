@@ -0,0 +1,30 @@
+use core::time::Duration;
+
+use embedded_hal::delay::DelayNs;
+use flipperzero::furi::thread;
+use flipperzero_sys as sys;
+
+/// [`embedded_hal::delay::DelayNs`] implementation using Furi delays.
+///
+/// Delays of a millisecond or more yield to other threads,
+/// shorter delays busy-wait with microsecond resolution.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Delay;
+
+impl DelayNs for Delay {
+    fn delay_ns(&mut self, ns: u32) {
+        self.delay_us(ns.div_ceil(1000));
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        if us >= 1000 {
+            thread::sleep(Duration::from_micros(us.into()));
+        } else {
+            unsafe { sys::furi_delay_us(us) }
+        }
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        thread::sleep(Duration::from_millis(ms.into()));
+    }
+}
@@ -1,2 +1,4 @@
+pub mod gpio;
+pub mod i2c;
 pub mod power;
 pub mod serial;
@@ -0,0 +1,352 @@
+use core::ffi::c_void;
+use core::marker::PhantomData;
+use core::pin;
+use core::ptr;
+
+use flipperzero_sys as sys;
+
+/// Pins on the external GPIO header.
+///
+/// Pins are plain identifiers, so exclusive use can't be checked. Configuring a pin is therefore
+/// `unsafe`, see [`Input::new`] and [`Interrupt::new`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pin {
+    /// Pin 2.
+    PA7,
+    /// Pin 3.
+    PA6,
+    /// Pin 4.
+    PA4,
+    /// Pin 5.
+    PB3,
+    /// Pin 6.
+    PB2,
+    /// Pin 7.
+    PC3,
+    /// Pin 15.
+    PC1,
+    /// Pin 16.
+    PC0,
+}
+
+impl Pin {
+    /// Number of the pin on the external header.
+    pub const fn number(self) -> u8 {
+        match self {
+            Pin::PA7 => 2,
+            Pin::PA6 => 3,
+            Pin::PA4 => 4,
+            Pin::PB3 => 5,
+            Pin::PB2 => 6,
+            Pin::PC3 => 7,
+            Pin::PC1 => 15,
+            Pin::PC0 => 16,
+        }
+    }
+
+    /// Get pointer to raw [`sys::GpioPin`].
+    pub fn as_ptr(self) -> *const sys::GpioPin {
+        match self {
+            Pin::PA7 => &raw const sys::gpio_ext_pa7,
+            Pin::PA6 => &raw const sys::gpio_ext_pa6,
+            Pin::PA4 => &raw const sys::gpio_ext_pa4,
+            Pin::PB3 => &raw const sys::gpio_ext_pb3,
+            Pin::PB2 => &raw const sys::gpio_ext_pb2,
+            Pin::PC3 => &raw const sys::gpio_ext_pc3,
+            Pin::PC1 => &raw const sys::gpio_ext_pc1,
+            Pin::PC0 => &raw const sys::gpio_ext_pc0,
+        }
+    }
+
+    /// Configure as a digital input.
+    ///
+    /// # Safety
+    ///
+    /// See [`Input::new`].
+    pub unsafe fn into_input(self, pull: Pull) -> Input {
+        unsafe { Input::new(self, pull) }
+    }
+
+    /// Configure as a digital output, initially at `level`.
+    ///
+    /// # Safety
+    ///
+    /// See [`Output::new`].
+    pub unsafe fn into_output(self, output_type: OutputType, level: bool) -> Output {
+        unsafe { Output::new(self, output_type, level) }
+    }
+
+    /// Configure as a digital input calling `callback` on `edge`.
+    ///
+    /// # Safety
+    ///
+    /// See [`Interrupt::new`].
+    pub unsafe fn into_interrupt<'a, C: Fn() + Sync>(self, pull: Pull, edge: Edge, callback: pin::Pin<&'a mut C>) -> Interrupt<'a> {
+        unsafe { Interrupt::new(self, pull, edge, callback) }
+    }
+
+    fn init(self, mode: sys::GpioMode, pull: Pull) {
+        unsafe { sys::furi_hal_gpio_init(self.as_ptr(), mode, pull.to_raw(), sys::GpioSpeedLow) }
+    }
+
+    /// Return to analog mode, which has the lowest power consumption and disconnects the pin.
+    fn reset(self) {
+        self.init(sys::GpioModeAnalog, Pull::None)
+    }
+}
+
+/// Internal pull-up/pull-down resistor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Pull {
+    #[default]
+    None,
+    Up,
+    Down,
+}
+
+impl Pull {
+    fn to_raw(self) -> sys::GpioPull {
+        match self {
+            Pull::None => sys::GpioPullNo,
+            Pull::Up => sys::GpioPullUp,
+            Pull::Down => sys::GpioPullDown,
+        }
+    }
+}
+
+/// Output driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputType {
+    /// Drive both high and low.
+    #[default]
+    PushPull,
+    /// Only drive low, high needs a pull-up.
+    OpenDrain,
+}
+
+/// Input edge that triggers an interrupt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    Rising,
+    Falling,
+    Both,
+}
+
+impl Edge {
+    fn mode(self) -> sys::GpioMode {
+        match self {
+            Edge::Rising => sys::GpioModeInterruptRise,
+            Edge::Falling => sys::GpioModeInterruptFall,
+            Edge::Both => sys::GpioModeInterruptRiseFall,
+        }
+    }
+}
+
+/// Pin configured as a digital input.
+///
+/// The pin is returned to analog mode when dropped.
+pub struct Input {
+    pin: Pin,
+}
+
+impl Input {
+    /// Configure `pin` as a digital input.
+    ///
+    /// # Safety
+    ///
+    /// `pin` must not be used by anything else (another driver in this app, another app or a firmware
+    /// service such as the GPIO or iButton apps) until the returned value is dropped or converted,
+    /// as it would be reconfigured underneath them.
+    pub unsafe fn new(pin: Pin, pull: Pull) -> Self {
+        pin.init(sys::GpioModeInput, pull);
+
+        Input { pin }
+    }
+
+    pub fn pin(&self) -> Pin {
+        self.pin
+    }
+
+    /// Input is at logic high level.
+    pub fn is_high(&self) -> bool {
+        unsafe { sys::furi_hal_gpio_read(self.pin.as_ptr()) }
+    }
+
+    /// Input is at logic low level.
+    pub fn is_low(&self) -> bool {
+        !self.is_high()
+    }
+
+    /// Reconfigure as a digital output, initially at `level`.
+    pub fn into_output(self, output_type: OutputType, level: bool) -> Output {
+        // SAFETY: The pin was exclusive to this input
+        unsafe { Output::new(self.into_analog(), output_type, level) }
+    }
+
+    /// Return the pin to analog mode.
+    pub fn into_analog(self) -> Pin {
+        let pin = self.pin;
+        drop(self);
+
+        pin
+    }
+}
+
+impl Drop for Input {
+    fn drop(&mut self) {
+        self.pin.reset();
+    }
+}
+
+/// Pin configured as a digital output.
+///
+/// The pin is returned to analog mode when dropped.
+pub struct Output {
+    pin: Pin,
+}
+
+impl Output {
+    /// Configure `pin` as a digital output, initially at `level`.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Input::new`].
+    pub unsafe fn new(pin: Pin, output_type: OutputType, level: bool) -> Self {
+        // Set level first to avoid a glitch when the output is enabled
+        unsafe { sys::furi_hal_gpio_write(pin.as_ptr(), level) };
+
+        let mode = match output_type {
+            OutputType::PushPull => sys::GpioModeOutputPushPull,
+            OutputType::OpenDrain => sys::GpioModeOutputOpenDrain,
+        };
+        pin.init(mode, Pull::None);
+
+        Output { pin }
+    }
+
+    pub fn pin(&self) -> Pin {
+        self.pin
+    }
+
+    /// Set output level.
+    pub fn set(&mut self, level: bool) {
+        unsafe { sys::furi_hal_gpio_write(self.pin.as_ptr(), level) }
+    }
+
+    pub fn set_high(&mut self) {
+        self.set(true)
+    }
+
+    pub fn set_low(&mut self) {
+        self.set(false)
+    }
+
+    /// Level actually present on the pin.
+    ///
+    /// May differ from the set level for an open drain output.
+    pub fn is_high(&self) -> bool {
+        unsafe { sys::furi_hal_gpio_read(self.pin.as_ptr()) }
+    }
+
+    /// Reconfigure as a digital input.
+    pub fn into_input(self, pull: Pull) -> Input {
+        // SAFETY: The pin was exclusive to this driver
+        unsafe { Input::new(self.into_analog(), pull) }
+    }
+
+    /// Return the pin to analog mode.
+    pub fn into_analog(self) -> Pin {
+        let pin = self.pin;
+        drop(self);
+
+        pin
+    }
+}
+
+impl Drop for Output {
+    fn drop(&mut self) {
+        self.pin.reset();
+    }
+}
+
+/// Pin configured as a digital input with an interrupt callback.
+///
+/// The callback is removed and the pin returned to analog mode when dropped.
+pub struct Interrupt<'a> {
+    pin: Pin,
+    _callback: PhantomData<&'a mut ()>,
+}
+
+impl<'a> Interrupt<'a> {
+    /// Configure `pin` as a digital input calling `callback` on `edge`.
+    ///
+    /// The callback runs in interrupt context, so it must be quick and must not block.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Input::new`]. In addition, no interrupt may be in use on the other pin sharing its
+    /// EXTI line: PB3 (pin 5) and PC3 (pin 7) are both on line 3, which has a single callback slot.
+    pub unsafe fn new<C: Fn() + Sync>(pin: Pin, pull: Pull, edge: Edge, mut callback: pin::Pin<&'a mut C>) -> Self {
+        let context = unsafe { ptr::from_mut(pin::Pin::into_inner_unchecked(callback.as_mut())) };
+
+        pin.init(edge.mode(), pull);
+        unsafe { sys::furi_hal_gpio_add_int_callback(pin.as_ptr(), Some(gpio_interrupt_callback::<C>), context.cast()) };
+
+        Interrupt {
+            pin,
+            _callback: PhantomData,
+        }
+    }
+
+    pub fn pin(&self) -> Pin {
+        self.pin
+    }
+
+    /// Input is at logic high level.
+    pub fn is_high(&self) -> bool {
+        unsafe { sys::furi_hal_gpio_read(self.pin.as_ptr()) }
+    }
+
+    /// Input is at logic low level.
+    pub fn is_low(&self) -> bool {
+        !self.is_high()
+    }
+
+    /// Resume calling the callback.
+    pub fn enable(&mut self) {
+        unsafe { sys::furi_hal_gpio_enable_int_callback(self.pin.as_ptr()) }
+    }
+
+    /// Stop calling the callback until [`Self::enable`] is called.
+    pub fn disable(&mut self) {
+        unsafe { sys::furi_hal_gpio_disable_int_callback(self.pin.as_ptr()) }
+    }
+
+    /// Remove the callback and reconfigure as a plain digital input.
+    pub fn into_input(self, pull: Pull) -> Input {
+        // SAFETY: The pin was exclusive to this driver
+        unsafe { Input::new(self.into_analog(), pull) }
+    }
+
+    /// Remove the callback and return the pin to analog mode.
+    pub fn into_analog(self) -> Pin {
+        let pin = self.pin;
+        drop(self);
+
+        pin
+    }
+}
+
+impl Drop for Interrupt<'_> {
+    fn drop(&mut self) {
+        // Ensure the callback no longer references the closure
+        unsafe { sys::furi_hal_gpio_remove_int_callback(self.pin.as_ptr()) };
+        self.pin.reset();
+    }
+}
+
+unsafe extern "C" fn gpio_interrupt_callback<C: Fn() + Sync>(context: *mut c_void) {
+    let callback: *const C = context.cast();
+
+    unsafe { (*callback)() };
+}
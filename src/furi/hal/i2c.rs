@@ -0,0 +1,124 @@
+use embedded_hal::i2c::{self as hal, ErrorKind, NoAcknowledgeSource, Operation, SevenBitAddress};
+use flipperzero::furi::time::FuriDuration;
+use flipperzero::gpio::i2c;
+use flipperzero_sys as sys;
+
+/// [`embedded_hal::i2c::I2c`] implementation for a Furi I2C bus.
+///
+/// Furi only reports whether a transfer succeeded, so errors are [`ErrorKind::Other`],
+/// except for a failed probe (an empty write) which is [`NoAcknowledgeSource::Address`].
+pub struct I2cBus {
+    bus: i2c::BusHandle,
+    /// Raw handle of `bus`, which [`i2c::BusHandle`] doesn't expose.
+    handle: *const sys::FuriHalI2cBusHandle,
+    timeout_ms: u64,
+}
+
+impl I2cBus {
+    /// Default timeout for each transfer.
+    pub const DEFAULT_TIMEOUT_MS: u64 = 1000;
+
+    /// Acquire the internal (power) I2C bus.
+    pub fn internal() -> Self {
+        Self::acquire(i2c::Bus::INTERNAL, &raw const sys::furi_hal_i2c_handle_power)
+    }
+
+    /// Acquire the external I2C bus (pins 15 and 16).
+    pub fn external() -> Self {
+        Self::acquire(i2c::Bus::EXTERNAL, &raw const sys::furi_hal_i2c_handle_external)
+    }
+
+    fn acquire(bus: i2c::Bus, handle: *const sys::FuriHalI2cBusHandle) -> Self {
+        I2cBus { bus: bus.acquire(), handle, timeout_ms: Self::DEFAULT_TIMEOUT_MS }
+    }
+
+    /// Set the timeout for each transfer.
+    pub fn set_timeout_ms(&mut self, timeout_ms: u64) {
+        self.timeout_ms = timeout_ms;
+    }
+
+    /// Borrow the underlying bus handle.
+    pub fn handle(&mut self) -> &mut i2c::BusHandle {
+        &mut self.bus
+    }
+
+    /// Release the underlying bus handle.
+    pub fn into_inner(self) -> i2c::BusHandle {
+        self.bus
+    }
+
+    fn timeout(&self) -> FuriDuration {
+        FuriDuration::from_millis(self.timeout_ms)
+    }
+}
+
+impl hal::ErrorType for I2cBus {
+    type Error = ErrorKind;
+}
+
+impl hal::I2c<SevenBitAddress> for I2cBus {
+    /// Perform a transaction.
+    ///
+    /// All operations are performed as one transfer: adjacent operations of the same type continue
+    /// each other and a change of type sends a repeated START.
+    /// Empty operations are only supported on their own, as a probe; in a longer sequence they fail
+    /// with [`ErrorKind::Other`].
+    fn transaction(&mut self, address: SevenBitAddress, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+        match operations {
+            [] => return Ok(()),
+            [Operation::Write([])] => {
+                return match self.bus.is_device_ready(i2c::DeviceAddress::new(address), self.timeout()) {
+                    true => Ok(()),
+                    false => Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+                };
+            }
+            _ => {}
+        }
+
+        let is_empty = |operation: &Operation<'_>| match operation {
+            Operation::Write(data) => data.is_empty(),
+            Operation::Read(buf) => buf.is_empty(),
+        };
+        if operations.iter().any(is_empty) {
+            return Err(ErrorKind::Other);
+        }
+
+        // Furi takes the address in 8-bit form, like `i2c::DeviceAddress`
+        let address = u16::from(address << 1);
+        let timeout = self.timeout().as_millis() as u32;
+        let is_read = |operation: &Operation<'_>| matches!(operation, Operation::Read(_));
+
+        let mut previous_read = None;
+        for n in 0..operations.len() {
+            let next_read = operations.get(n + 1).map(is_read);
+            let read = is_read(&operations[n]);
+
+            let begin = match previous_read {
+                None => sys::FuriHalI2cBeginStart,
+                Some(previous) if previous == read => sys::FuriHalI2cBeginResume,
+                Some(_) => sys::FuriHalI2cBeginRestart,
+            };
+            let end = match next_read {
+                None => sys::FuriHalI2cEndStop,
+                Some(next) if next == read => sys::FuriHalI2cEndPause,
+                Some(_) => sys::FuriHalI2cEndAwaitRestart,
+            };
+
+            let ok = match &mut operations[n] {
+                Operation::Write(data) => unsafe {
+                    sys::furi_hal_i2c_tx_ext(self.handle, address, false, data.as_ptr(), data.len(), begin, end, timeout)
+                },
+                Operation::Read(buf) => unsafe {
+                    sys::furi_hal_i2c_rx_ext(self.handle, address, false, buf.as_mut_ptr(), buf.len(), begin, end, timeout)
+                },
+            };
+            if !ok {
+                return Err(ErrorKind::Other);
+            }
+
+            previous_read = Some(read);
+        }
+
+        Ok(())
+    }
+}
@@ -60,6 +60,56 @@ impl Record<Power> {
     }
 }
 
+/// Battery charge (%) below which the 5V output may not be able to power a sensor.
+pub const OTG_LOW_BATTERY_PERCENT: u8 = 10;
+
+/// Get battery charge (%).
+pub fn battery_percent() -> u8 {
+    unsafe { sys::furi_hal_power_get_pct() }
+}
+
+/// RAII guard for the 5V output on GPIO pin 1.
+///
+/// The output is restored to its previous state when dropped, so it stays on
+/// if it was already enabled from the GPIO menu.
+pub struct OtgGuard {
+    was_enabled: bool,
+}
+
+impl OtgGuard {
+    /// Enable 5V output.
+    ///
+    /// Returns `None` if the output could not be enabled, for example if the battery is too low.
+    pub fn enable() -> Option<Self> {
+        let was_enabled = unsafe { sys::furi_hal_power_is_otg_enabled() };
+        if !was_enabled && !unsafe { sys::furi_hal_power_enable_otg() } {
+            return None;
+        }
+
+        Some(OtgGuard { was_enabled })
+    }
+
+    /// 5V output is still on.
+    ///
+    /// The output is switched off if it is overloaded or the battery runs out.
+    pub fn is_active(&self) -> bool {
+        unsafe { sys::furi_hal_power_is_otg_enabled() }
+    }
+
+    /// Battery is too low to reliably power a sensor.
+    pub fn is_battery_low(&self) -> bool {
+        battery_percent() < OTG_LOW_BATTERY_PERCENT
+    }
+}
+
+impl Drop for OtgGuard {
+    fn drop(&mut self) {
+        if !self.was_enabled {
+            unsafe { sys::furi_hal_power_disable_otg() }
+        }
+    }
+}
+
 #[repr(C)]
 pub struct PowerEvent {
     pub type_: PowerEventType,
@@ -1,12 +1,16 @@
+use core::convert::Infallible;
 use core::ffi::c_void;
+use core::fmt;
 use core::num::NonZero;
 use core::ptr::{self, NonNull};
-use core::sync::atomic::{AtomicPtr, Ordering};
+use core::sync::atomic::{AtomicPtr, AtomicU32, AtomicU8, Ordering};
+
+use embedded_io::{ErrorKind, ErrorType, Read, ReadReady, Write};
 
 use flipperzero::furi::stream_buffer::StreamBuffer;
 use flipperzero::furi::thread::{self, ThreadId};
 use flipperzero::furi::time::FuriDuration;
-use flipperzero::{debug, furi, info, trace, warn};
+use flipperzero::{debug, furi, info};
 use flipperzero_sys::{self as sys, HasFlag};
 use sys::furi::FuriBox;
 
@@ -91,8 +95,77 @@ impl SerialHandle {
         unsafe { sys::furi_hal_serial_tx_wait_complete(self.handle.as_ptr()) }
     }
 
-    pub fn async_receiver<F: FnMut(&[u8])>(&self, on_rx: F) -> AsyncSerialReceiver<'_, F> {
-        AsyncSerialReceiver::new(self, on_rx)
+    /// Receive data on a worker thread, calling `on_rx` for each received chunk.
+    ///
+    /// Use [`SerialHandle::async_event_receiver`] to also be notified of idle and line errors.
+    pub fn async_receiver<F: FnMut(&[u8])>(&self, mut on_rx: F) -> AsyncSerialReceiver<'_, impl FnMut(SerialEvent<'_>)> {
+        self.async_event_receiver(move |event| {
+            if let SerialEvent::Data(data) = event {
+                on_rx(data)
+            }
+        })
+    }
+
+    /// Receive data on a worker thread, calling `on_event` for received data, idle and line errors.
+    pub fn async_event_receiver<F: FnMut(SerialEvent<'_>)>(&self, on_event: F) -> AsyncSerialReceiver<'_, F> {
+        AsyncSerialReceiver::new(self, RxMode::Interrupt, on_event)
+    }
+
+    /// Like [`SerialHandle::async_receiver`], but received data is transferred by DMA.
+    ///
+    /// Data is passed on in chunks when the line goes idle or the DMA buffer is half full,
+    /// rather than interrupting for every byte. Use this for higher baud rates.
+    pub fn dma_receiver<F: FnMut(&[u8])>(&self, mut on_rx: F) -> AsyncSerialReceiver<'_, impl FnMut(SerialEvent<'_>)> {
+        self.dma_event_receiver(move |event| {
+            if let SerialEvent::Data(data) = event {
+                on_rx(data)
+            }
+        })
+    }
+
+    /// Like [`SerialHandle::async_event_receiver`], but received data is transferred by DMA.
+    pub fn dma_event_receiver<F: FnMut(SerialEvent<'_>)>(&self, on_event: F) -> AsyncSerialReceiver<'_, F> {
+        AsyncSerialReceiver::new(self, RxMode::Dma, on_event)
+    }
+
+    /// Start receiving into a blocking [`embedded_io::Read`] implementation.
+    ///
+    /// Reception starts immediately and stops when the reader is dropped.
+    pub fn reader(&self) -> SerialReader<'_> {
+        SerialReader::new(self)
+    }
+}
+
+impl ErrorType for SerialHandle {
+    type Error = Infallible;
+}
+
+impl Write for SerialHandle {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        Write::write(&mut &*self, buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Write::flush(&mut &*self)
+    }
+}
+
+impl ErrorType for &SerialHandle {
+    type Error = Infallible;
+}
+
+/// Allows writing while a [`SerialReader`] borrows the handle.
+impl Write for &SerialHandle {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.tx(buf);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.tx_wait_complete();
+
+        Ok(())
     }
 }
 
@@ -102,6 +175,264 @@ impl Drop for SerialHandle {
     }
 }
 
+/// Serial receive errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SerialError {
+    /// No data was received before the timeout.
+    Timeout,
+    /// Data was received faster than it could be read, so some was lost.
+    Overrun,
+    /// Stop bit was not detected (e.g. baud rate mismatch).
+    Framing,
+    /// Noise was detected on the line.
+    Noise,
+}
+
+impl SerialError {
+    const FLAG_OVERRUN: u8 = 1 << 0;
+    const FLAG_FRAMING: u8 = 1 << 1;
+    const FLAG_NOISE: u8 = 1 << 2;
+
+    /// Line error flags raised by `event`.
+    fn flags(event: sys::FuriHalSerialRxEvent) -> u8 {
+        let mut flags = 0;
+
+        if event.has_flag(sys::FuriHalSerialRxEventOverrunError) {
+            flags |= Self::FLAG_OVERRUN;
+        }
+
+        if event.has_flag(sys::FuriHalSerialRxEventFrameError) {
+            flags |= Self::FLAG_FRAMING;
+        }
+
+        if event.has_flag(sys::FuriHalSerialRxEventNoiseError) {
+            flags |= Self::FLAG_NOISE;
+        }
+
+        flags
+    }
+
+    /// Most severe line error in `flags`.
+    fn from_flags(flags: u8) -> Option<Self> {
+        if flags & Self::FLAG_OVERRUN != 0 {
+            Some(SerialError::Overrun)
+        } else if flags & Self::FLAG_FRAMING != 0 {
+            Some(SerialError::Framing)
+        } else if flags & Self::FLAG_NOISE != 0 {
+            Some(SerialError::Noise)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for SerialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerialError::Timeout => f.write_str("timed out"),
+            SerialError::Overrun => f.write_str("overrun"),
+            SerialError::Framing => f.write_str("framing error"),
+            SerialError::Noise => f.write_str("noise error"),
+        }
+    }
+}
+
+impl embedded_io::Error for SerialError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            SerialError::Timeout => ErrorKind::TimedOut,
+            SerialError::Overrun => ErrorKind::Other,
+            SerialError::Framing | SerialError::Noise => ErrorKind::InvalidData,
+        }
+    }
+}
+
+/// Cumulative line error counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LineErrors {
+    /// Received data was lost, either in hardware or because the receive buffer was full.
+    pub overrun: u32,
+    pub framing: u32,
+    pub noise: u32,
+}
+
+impl LineErrors {
+    pub fn total(&self) -> u32 {
+        self.overrun.saturating_add(self.framing).saturating_add(self.noise)
+    }
+}
+
+/// Line error counters shared with the receive interrupt.
+#[derive(Default)]
+struct LineErrorCounters {
+    overrun: AtomicU32,
+    framing: AtomicU32,
+    noise: AtomicU32,
+}
+
+impl LineErrorCounters {
+    /// Count the line errors in `flags` (see [`SerialError::flags`]).
+    fn record(&self, flags: u8) {
+        let counters = [
+            (SerialError::FLAG_OVERRUN, &self.overrun),
+            (SerialError::FLAG_FRAMING, &self.framing),
+            (SerialError::FLAG_NOISE, &self.noise),
+        ];
+
+        for (flag, counter) in counters {
+            if flags & flag != 0 {
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn load(&self) -> LineErrors {
+        LineErrors {
+            overrun: self.overrun.load(Ordering::Relaxed),
+            framing: self.framing.load(Ordering::Relaxed),
+            noise: self.noise.load(Ordering::Relaxed),
+        }
+    }
+
+    fn clear(&self) {
+        self.overrun.store(0, Ordering::Relaxed);
+        self.framing.store(0, Ordering::Relaxed);
+        self.noise.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Blocking serial reader.
+///
+/// Received bytes are buffered by an interrupt callback until they are read.
+pub struct SerialReader<'a> {
+    handle: &'a SerialHandle,
+    context: FuriBox<ReaderContext>,
+    timeout_ms: u64,
+}
+
+struct ReaderContext {
+    rx_stream: StreamBuffer,
+    /// Line errors since the last read (see [`SerialError::flags`]).
+    errors: AtomicU8,
+}
+
+impl<'a> SerialReader<'a> {
+    /// Default time to wait for data in [`Read::read`].
+    pub const DEFAULT_TIMEOUT_MS: u64 = 1000;
+
+    fn new(handle: &'a SerialHandle) -> Self {
+        let mut context = FuriBox::new(ReaderContext {
+            rx_stream: StreamBuffer::new(NonZero::new(2048).unwrap(), 1),
+            errors: AtomicU8::new(0),
+        });
+
+        unsafe {
+            sys::furi_hal_serial_async_rx_start(
+                handle.as_ptr(),
+                Some(serial_reader_rx_callback),
+                FuriBox::as_mut_ptr(&mut context) as *mut _,
+                true,
+            );
+        }
+
+        SerialReader {
+            handle,
+            context,
+            timeout_ms: Self::DEFAULT_TIMEOUT_MS,
+        }
+    }
+
+    /// Set how long [`Read::read`] waits for data before returning [`SerialError::Timeout`].
+    pub fn set_timeout_ms(&mut self, timeout_ms: u64) {
+        self.timeout_ms = timeout_ms;
+    }
+
+    /// Serial handle, e.g. for writing a request.
+    pub fn handle(&self) -> &'a SerialHandle {
+        self.handle
+    }
+
+    /// Discard all buffered data and line errors.
+    pub fn clear(&mut self) {
+        let mut buf = [0u8; SERIAL_WORKER_BUFFER_LEN];
+        // SAFETY: The reader is the only receiver and `&mut self` prevents concurrent reads.
+        while unsafe { self.context.rx_stream.receive(&mut buf, FuriDuration::ZERO) } > 0 {}
+
+        self.context.errors.store(0, Ordering::Relaxed);
+    }
+}
+
+impl ErrorType for SerialReader<'_> {
+    type Error = SerialError;
+}
+
+impl Read for SerialReader<'_> {
+    /// Read at least one byte, waiting up to the timeout.
+    ///
+    /// A line error is returned once, ahead of all buffered data, including data received before the error.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if let Some(err) = SerialError::from_flags(self.context.errors.swap(0, Ordering::Relaxed)) {
+            return Err(err);
+        }
+
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        // SAFETY: The reader is the only receiver and `&mut self` prevents concurrent reads.
+        let timeout = FuriDuration::from_millis(self.timeout_ms);
+        match unsafe { self.context.rx_stream.receive(buf, timeout) } {
+            0 => Err(SerialError::Timeout),
+            len => Ok(len),
+        }
+    }
+}
+
+impl ReadReady for SerialReader<'_> {
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.context.errors.load(Ordering::Relaxed) != 0 || !self.context.rx_stream.is_empty())
+    }
+}
+
+/// Writes to the serial handle, so the reader can be used as a full duplex port.
+impl Write for SerialReader<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        Write::write(&mut &*self.handle, buf).map_err(|never| match never {})
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Write::flush(&mut &*self.handle).map_err(|never| match never {})
+    }
+}
+
+impl Drop for SerialReader<'_> {
+    fn drop(&mut self) {
+        // Ensure that callback is removed so it no longer references `ReaderContext`.
+        unsafe { sys::furi_hal_serial_async_rx_stop(self.handle.as_ptr()) }
+    }
+}
+
+unsafe extern "C" fn serial_reader_rx_callback(
+    handle: *mut sys::FuriHalSerialHandle,
+    event: sys::FuriHalSerialRxEvent,
+    context: *mut c_void,
+) {
+    let context = context.cast_const() as *const ReaderContext;
+
+    if event.has_flag(sys::FuriHalSerialRxEventData) {
+        let data = [sys::furi_hal_serial_async_rx(handle)];
+
+        if (*context).rx_stream.send(&data, FuriDuration::ZERO) == 0 {
+            (*context).errors.fetch_or(SerialError::FLAG_OVERRUN, Ordering::Relaxed);
+        }
+    }
+
+    let flags = SerialError::flags(event);
+    if flags != 0 {
+        (*context).errors.fetch_or(flags, Ordering::Relaxed);
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct WorkerEvent(u32);
 
@@ -126,6 +457,25 @@ impl WorkerEvent {
         | Self::FLAG_FRAMING_ERROR
         | Self::FLAG_NOISE_ERROR;
 
+    /// Worker flags for the line errors in `flags` (see [`SerialError::flags`]).
+    fn from_line_errors(flags: u8) -> u32 {
+        let mut events = 0;
+
+        if flags & SerialError::FLAG_OVERRUN != 0 {
+            events |= Self::FLAG_OVERRUN_ERROR;
+        }
+
+        if flags & SerialError::FLAG_FRAMING != 0 {
+            events |= Self::FLAG_FRAMING_ERROR;
+        }
+
+        if flags & SerialError::FLAG_NOISE != 0 {
+            events |= Self::FLAG_NOISE_ERROR;
+        }
+
+        events
+    }
+
     pub fn is_stop(self) -> bool {
         self.0 & Self::FLAG_STOP != 0
     }
@@ -155,30 +505,65 @@ impl WorkerEvent {
     }
 }
 
+/// Event passed to the callback of an [`AsyncSerialReceiver`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SerialEvent<'a> {
+    /// Data was received.
+    Data(&'a [u8]),
+    /// The line went idle after receiving data, e.g. at the end of a frame.
+    Idle,
+    /// A line error was detected.
+    ///
+    /// Errors are reported after the [`SerialEvent::Data`] received at the same time, so the affected
+    /// bytes may already have been passed on. Any data that was partially received around the error
+    /// should be discarded.
+    Error(SerialError),
+}
+
+/// How received data gets from the UART to the worker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RxMode {
+    /// Interrupt for every byte.
+    Interrupt,
+    /// DMA, interrupting on idle or when the DMA buffer is half full.
+    Dma,
+}
+
+/// Receives data on a worker thread.
+///
+/// For each wakeup of the worker, the callback is passed the received data,
+/// then any line errors and finally idle.
 pub struct AsyncSerialReceiver<'a, F>
 where
-    F: FnMut(&[u8]),
+    F: FnMut(SerialEvent<'_>),
 {
     handle: &'a SerialHandle,
+    mode: RxMode,
+    started: bool,
     context: FuriBox<Context<F>>,
+    /// Boxed separately so it can be read without referencing `Context` while the worker is running.
+    line_errors: FuriBox<LineErrorCounters>,
 }
 
-struct Context<F: FnMut(&[u8])> {
+struct Context<F: FnMut(SerialEvent<'_>)> {
     rx_stream: StreamBuffer,
-    on_rx: F,
+    on_event: F,
+    line_errors: *const LineErrorCounters,
     worker_thread: AtomicPtr<sys::FuriThread>,
 }
 
 impl<'a, F> AsyncSerialReceiver<'a, F>
 where
-    F: FnMut(&[u8]),
+    F: FnMut(SerialEvent<'_>),
 {
-    fn new(serial_handle: &'a SerialHandle, on_rx: F) -> Self {
+    fn new(serial_handle: &'a SerialHandle, mode: RxMode, on_event: F) -> Self {
         let rx_stream = StreamBuffer::new(NonZero::new(2048).unwrap(), 1);
+        let line_errors = FuriBox::new(LineErrorCounters::default());
 
         let mut context = FuriBox::new(Context {
             rx_stream,
-            on_rx,
+            on_event,
+            line_errors: &raw const *line_errors,
             worker_thread: AtomicPtr::new(ptr::null_mut()),
         });
 
@@ -203,31 +588,69 @@ where
 
         AsyncSerialReceiver {
             handle: serial_handle,
+            mode,
+            started: false,
             context,
+            line_errors,
         }
     }
 
     pub fn start(&mut self) {
+        if self.started {
+            return;
+        }
+
+        // SAFETY: Grabbing the context pointer with `as_mut_ptr` is fine,
+        // since it doesn't create an intermediate referece.
+        let context = FuriBox::as_mut_ptr(&mut self.context) as *mut _;
+
         unsafe {
-            // SAFETY: Grabbing the context pointer with `as_mut_ptr` is fine,
-            // since it doesn't create an intermediate referece.
-            sys::furi_hal_serial_async_rx_start(
-                self.handle.as_ptr(),
-                Some(async_serial_receiver_rx_callback::<F>),
-                FuriBox::as_mut_ptr(&mut self.context) as *mut _,
-                true,
-            );
+            match self.mode {
+                RxMode::Interrupt => sys::furi_hal_serial_async_rx_start(
+                    self.handle.as_ptr(),
+                    Some(async_serial_receiver_rx_callback::<F>),
+                    context,
+                    true,
+                ),
+                RxMode::Dma => sys::furi_hal_serial_dma_rx_start(
+                    self.handle.as_ptr(),
+                    Some(async_serial_receiver_dma_rx_callback::<F>),
+                    context,
+                    true,
+                ),
+            }
         }
+
+        self.started = true;
     }
 
     pub fn stop(&mut self) {
+        if !self.started {
+            return;
+        }
+
         unsafe {
-            sys::furi_hal_serial_async_rx_stop(self.handle.as_ptr());
+            match self.mode {
+                RxMode::Interrupt => sys::furi_hal_serial_async_rx_stop(self.handle.as_ptr()),
+                RxMode::Dma => sys::furi_hal_serial_dma_rx_stop(self.handle.as_ptr()),
+            }
         }
+
+        self.started = false;
+    }
+
+    /// Line errors counted since the receiver was created or [`Self::clear_line_errors`] was called.
+    pub fn line_errors(&self) -> LineErrors {
+        self.line_errors.load()
+    }
+
+    /// Reset the line error counts.
+    pub fn clear_line_errors(&self) {
+        self.line_errors.clear()
     }
 }
 
-impl<F: FnMut(&[u8])> Drop for AsyncSerialReceiver<'_, F> {
+impl<F: FnMut(SerialEvent<'_>)> Drop for AsyncSerialReceiver<'_, F> {
     fn drop(&mut self) {
         // Ensure that callback is removed so it no longer references `Context`.
         self.stop();
@@ -252,7 +675,7 @@ impl<F: FnMut(&[u8])> Drop for AsyncSerialReceiver<'_, F> {
     }
 }
 
-unsafe extern "C" fn async_serial_receiver_rx_callback<F: FnMut(&[u8])>(
+unsafe extern "C" fn async_serial_receiver_rx_callback<F: FnMut(SerialEvent<'_>)>(
     handle: *mut sys::FuriHalSerialHandle,
     event: sys::FuriHalSerialRxEvent,
     context: *mut c_void,
@@ -260,11 +683,14 @@ unsafe extern "C" fn async_serial_receiver_rx_callback<F: FnMut(&[u8])>(
     let context = context.cast_const() as *const Context<F>;
 
     let mut flags = 0u32;
+    let mut line_errors = SerialError::flags(event);
 
     if event.has_flag(sys::FuriHalSerialRxEventData) {
         let data = [sys::furi_hal_serial_async_rx(handle)];
 
-        (*context).rx_stream.send(&data, FuriDuration::ZERO);
+        if (*context).rx_stream.send(&data, FuriDuration::ZERO) == 0 {
+            line_errors |= SerialError::FLAG_OVERRUN;
+        }
         flags |= WorkerEvent::FLAG_DATA;
     }
 
@@ -272,20 +698,57 @@ unsafe extern "C" fn async_serial_receiver_rx_callback<F: FnMut(&[u8])>(
         flags |= WorkerEvent::FLAG_IDLE;
     }
 
-    if event.has_flag(sys::FuriHalSerialRxEventOverrunError) {
-        flags |= WorkerEvent::FLAG_OVERRUN_ERROR;
+    if line_errors != 0 {
+        (*(*context).line_errors).record(line_errors);
+        flags |= WorkerEvent::from_line_errors(line_errors);
+    }
+
+    let worker_thread = (*context).worker_thread.load(Ordering::Acquire);
+    if flags != 0 && !worker_thread.is_null() {
+        let thread_id = unsafe { ThreadId::from_furi_thread(worker_thread) };
+        thread::set_flags(thread_id, flags).unwrap();
+    }
+}
+
+unsafe extern "C" fn async_serial_receiver_dma_rx_callback<F: FnMut(SerialEvent<'_>)>(
+    handle: *mut sys::FuriHalSerialHandle,
+    event: sys::FuriHalSerialRxEvent,
+    data_len: usize,
+    context: *mut c_void,
+) {
+    let context = context.cast_const() as *const Context<F>;
+
+    let mut flags = 0u32;
+    let mut line_errors = SerialError::flags(event);
+
+    // Data is available on both half-buffer (data) and idle events.
+    // Move everything out of the DMA buffer, so it can't be overwritten before the worker runs
+    let mut remaining = data_len;
+    while remaining > 0 {
+        let mut data = [0u8; SERIAL_WORKER_BUFFER_LEN];
+        let len = sys::furi_hal_serial_dma_rx(handle, data.as_mut_ptr(), remaining.min(data.len()));
+        if len == 0 {
+            break;
+        }
+
+        if (*context).rx_stream.send(&data[..len], FuriDuration::ZERO) < len {
+            line_errors |= SerialError::FLAG_OVERRUN;
+        }
+        flags |= WorkerEvent::FLAG_DATA;
+        remaining -= len;
     }
 
-    if event.has_flag(sys::FuriHalSerialRxEventFrameError) {
-        flags |= WorkerEvent::FLAG_FRAMING_ERROR;
+    if event.has_flag(sys::FuriHalSerialRxEventIdle) {
+        flags |= WorkerEvent::FLAG_IDLE;
     }
 
-    if event.has_flag(sys::FuriHalSerialRxEventNoiseError) {
-        flags |= WorkerEvent::FLAG_NOISE_ERROR;
+    if line_errors != 0 {
+        (*(*context).line_errors).record(line_errors);
+        flags |= WorkerEvent::from_line_errors(line_errors);
     }
 
     let worker_thread = (*context).worker_thread.load(Ordering::Acquire);
-    if !worker_thread.is_null() {
+    if flags != 0 && !worker_thread.is_null() {
         let thread_id = unsafe { ThreadId::from_furi_thread(worker_thread) };
         thread::set_flags(thread_id, flags).unwrap();
     }
@@ -293,7 +756,7 @@ unsafe extern "C" fn async_serial_receiver_rx_callback<F: FnMut(&[u8])>(
 
 const SERIAL_WORKER_BUFFER_LEN: usize = 64;
 
-unsafe extern "C" fn async_serial_receiver_worker<F: FnMut(&[u8])>(context: *mut c_void) -> i32 {
+unsafe extern "C" fn async_serial_receiver_worker<F: FnMut(SerialEvent<'_>)>(context: *mut c_void) -> i32 {
     info!("Starting async worker");
     assert!(!context.is_null());
     let context = context.cast::<Context<F>>();
@@ -317,27 +780,27 @@ unsafe extern "C" fn async_serial_receiver_worker<F: FnMut(&[u8])>(context: *mut
                     break;
                 }
 
-                ((*context).on_rx)(&data[..len])
+                ((*context).on_event)(SerialEvent::Data(&data[..len]))
             }
         }
 
-        if events.is_rx_idle() {
-            trace!("idle");
-        }
-
         if events.is_error() {
             if events.is_overrun_error() {
-                warn!("overrun");
+                ((*context).on_event)(SerialEvent::Error(SerialError::Overrun))
             }
 
             if events.is_framing_error() {
-                warn!("framing error");
+                ((*context).on_event)(SerialEvent::Error(SerialError::Framing))
             }
 
             if events.is_noise_error() {
-                warn!("noise error");
+                ((*context).on_event)(SerialEvent::Error(SerialError::Noise))
             }
         }
+
+        if events.is_rx_idle() {
+            ((*context).on_event)(SerialEvent::Idle)
+        }
     }
 
     0
@@ -28,6 +28,10 @@ impl View {
         sys::view_set_draw_callback(self.as_ptr(), callback)
     }
 
+    pub unsafe fn set_input_callback(&self, callback: Option<unsafe extern "C" fn(*mut sys::InputEvent, *mut c_void) -> bool>) {
+        sys::view_set_input_callback(self.as_ptr(), callback)
+    }
+
     pub unsafe fn set_previous_callback(&self, callback: Option<unsafe extern "C" fn(*mut c_void) -> u32>) {
         sys::view_set_previous_callback(self.as_ptr(), callback)
     }
@@ -33,6 +33,14 @@ impl ViewDispatcher {
         unsafe { sys::view_dispatcher_set_event_callback_context(self.as_ptr(), context) }
     }
 
+    pub unsafe fn set_custom_event_callback(&self, callback: Option<unsafe extern "C" fn(*mut c_void, u32) -> bool>) {
+        unsafe { sys::view_dispatcher_set_custom_event_callback(self.as_ptr(), callback) }
+    }
+
+    pub fn send_custom_event(&self, event: u32) {
+        unsafe { sys::view_dispatcher_send_custom_event(self.as_ptr(), event) }
+    }
+
     pub unsafe fn set_tick_event_callback(&self, callback: Option<unsafe extern "C" fn(*mut c_void)>, tick_period: FuriDuration) {
         unsafe { sys::view_dispatcher_set_tick_event_callback(self.as_ptr(), callback, tick_period.as_ticks()) }
     }
@@ -0,0 +1,30 @@
+//! Humidity sources and conversions shared by sensor drivers.
+
+/// Source of ambient conditions for humidity compensation.
+pub trait HumiditySource {
+    /// Get temperature (degC) and relative humidity (%RH).
+    ///
+    /// Returns `None` if no valid reading is currently available.
+    fn temperature_humidity(&mut self) -> Option<(f32, f32)>;
+}
+
+/// Calculate absolute humidity (g/m³) from temperature (degC) and relative humidity (%RH).
+///
+/// Uses the Magnus formula as given in the SGP30 datasheet.
+pub fn absolute_humidity(temperature: f32, relative_humidity: f32) -> f32 {
+    let saturation_vapor_pressure = 6.112 * libm::expf(17.62 * temperature / (243.12 + temperature));
+
+    216.7 * (relative_humidity / 100.0 * saturation_vapor_pressure) / (273.15 + temperature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_absolute_humidity() {
+        // 25 degC at 50 %RH is approximately 11.5 g/m³
+        let value = absolute_humidity(25.0, 50.0);
+        assert!((value - 11.5).abs() < 0.1, "{value}");
+    }
+}
@@ -1,9 +1,18 @@
 #![no_std]
 
+pub mod crc;
+pub mod ens160;
+pub mod fmt;
 pub mod furi;
 pub mod gui;
+pub mod humidity;
 pub mod nicla_sense_env;
+pub mod sgp30;
 pub mod storage;
+pub mod transaction;
+pub mod voc;
+pub mod zp01;
+pub mod zphs01b;
 
 #[macro_export]
 macro_rules! printf {
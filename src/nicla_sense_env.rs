@@ -1,5 +1,54 @@
-use flipperzero::furi::time::FuriDuration;
-use flipperzero::gpio::i2c;
+use core::fmt;
+use core::ops::RangeInclusive;
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::{self, I2c};
+
+use crate::humidity::HumiditySource;
+
+/// Nicla Sense Env errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NiclaError {
+    /// I2C transfer failed.
+    I2c(i2c::ErrorKind),
+    /// Register contains a value that is not valid for it.
+    InvalidValue { register: u8, value: u8 },
+    /// Sensor has not produced a measurement yet.
+    NotReady,
+    /// Baud rate is not one of the rates supported by the board.
+    UnsupportedBaudRate(u32),
+    /// Board did not complete the operation in time.
+    Timeout,
+    /// Address is outside the range of valid 7-bit I2C addresses.
+    InvalidAddress(u8),
+    /// No board responded at the address.
+    NoDevice(u8),
+    /// Board accepted a new address but responds at neither the old nor the new one.
+    AddressUnknown,
+}
+
+impl NiclaError {
+    fn i2c(err: impl i2c::Error) -> Self {
+        NiclaError::I2c(err.kind())
+    }
+}
+
+impl fmt::Display for NiclaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NiclaError::I2c(err) => write!(f, "I2C error: {err}"),
+            NiclaError::InvalidValue { register, value } => {
+                write!(f, "invalid value 0x{value:02X} in register 0x{register:02X}")
+            }
+            NiclaError::NotReady => f.write_str("not ready"),
+            NiclaError::UnsupportedBaudRate(baud) => write!(f, "unsupported baud rate: {baud}"),
+            NiclaError::Timeout => f.write_str("timed out"),
+            NiclaError::InvalidAddress(address) => write!(f, "invalid address: 0x{address:02X}"),
+            NiclaError::NoDevice(address) => write!(f, "no device at 0x{address:02X}"),
+            NiclaError::AddressUnknown => f.write_str("board address unknown"),
+        }
+    }
+}
 
 #[repr(u8)]
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
@@ -22,28 +71,365 @@ pub enum IndoorSensorMode {
     Sulfur = 5,
 }
 
+impl TryFrom<u8> for OutdoorSensorMode {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(OutdoorSensorMode::Off),
+            1 => Ok(OutdoorSensorMode::Cleaning),
+            2 => Ok(OutdoorSensorMode::OutdoorAirQuality),
+            _ => Err(value),
+        }
+    }
+}
+
+impl TryFrom<u8> for IndoorSensorMode {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        use IndoorSensorMode::*;
+        match value {
+            0 => Ok(Off),
+            1 => Ok(Cleaning),
+            2 => Ok(IndoorAirQuality),
+            3 => Ok(IndoorAirQualityLowPower),
+            4 => Ok(PublicBuildingAirQuality),
+            5 => Ok(Sulfur),
+            _ => Err(value),
+        }
+    }
+}
+
+/// Set or clear `flag` in a register value.
+fn set_flag(register: &mut u8, flag: u8, value: bool) {
+    if value {
+        *register |= flag;
+    } else {
+        *register &= !flag;
+    }
+}
+
+/// Status register.
+///
+/// - bit 0: Temperature/humidity sensor enabled
+/// - bits 1..4: Indoor sensor mode
+/// - bits 4..6: Outdoor sensor mode
+/// - bit 6: Deep sleep
+/// - bit 7: Reset
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct NiclaStatus(pub u8);
+
+impl NiclaStatus {
+    const TEMPERATURE_ENABLED: u8 = 1 << 0;
+    const INDOOR_MODE_SHIFT: u8 = 1;
+    const INDOOR_MODE_MASK: u8 = 0x07;
+    const OUTDOOR_MODE_SHIFT: u8 = 4;
+    const OUTDOOR_MODE_MASK: u8 = 0x03;
+    const DEEP_SLEEP: u8 = 1 << 6;
+    const RESET: u8 = 1 << 7;
+
+    /// Temperature/humidity sensor is enabled.
+    pub fn temperature_enabled(self) -> bool {
+        self.0 & Self::TEMPERATURE_ENABLED != 0
+    }
+
+    pub fn set_temperature_enabled(&mut self, enabled: bool) {
+        set_flag(&mut self.0, Self::TEMPERATURE_ENABLED, enabled);
+    }
+
+    /// Mode of the indoor sensor.
+    ///
+    /// Returns `None` if the mode bits do not hold a valid mode.
+    pub fn indoor_mode(self) -> Option<IndoorSensorMode> {
+        IndoorSensorMode::try_from((self.0 >> Self::INDOOR_MODE_SHIFT) & Self::INDOOR_MODE_MASK).ok()
+    }
+
+    pub fn set_indoor_mode(&mut self, mode: IndoorSensorMode) {
+        self.0 = (self.0 & !(Self::INDOOR_MODE_MASK << Self::INDOOR_MODE_SHIFT))
+            | ((mode as u8) << Self::INDOOR_MODE_SHIFT);
+    }
+
+    /// Mode of the outdoor sensor.
+    ///
+    /// Returns `None` if the mode bits do not hold a valid mode.
+    pub fn outdoor_mode(self) -> Option<OutdoorSensorMode> {
+        OutdoorSensorMode::try_from((self.0 >> Self::OUTDOOR_MODE_SHIFT) & Self::OUTDOOR_MODE_MASK).ok()
+    }
+
+    pub fn set_outdoor_mode(&mut self, mode: OutdoorSensorMode) {
+        self.0 = (self.0 & !(Self::OUTDOOR_MODE_MASK << Self::OUTDOOR_MODE_SHIFT))
+            | ((mode as u8) << Self::OUTDOOR_MODE_SHIFT);
+    }
+
+    /// Board is in deep sleep.
+    pub fn deep_sleep(self) -> bool {
+        self.0 & Self::DEEP_SLEEP != 0
+    }
+
+    /// Board is resetting.
+    pub fn reset(self) -> bool {
+        self.0 & Self::RESET != 0
+    }
+}
+
+/// Board control register.
+///
+/// - bit 6: Enter deep sleep
+/// - bit 7: Reset board
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct NiclaControl(pub u8);
+
+impl NiclaControl {
+    const DEEP_SLEEP: u8 = 1 << 6;
+    const RESET: u8 = 1 << 7;
 
-pub struct NiclaSenseEnv<'a> {
-    bus: &'a mut i2c::BusHandle,
-    device: i2c::DeviceAddress,
+    pub fn deep_sleep(self) -> bool {
+        self.0 & Self::DEEP_SLEEP != 0
+    }
+
+    pub fn set_deep_sleep(&mut self, value: bool) {
+        set_flag(&mut self.0, Self::DEEP_SLEEP, value);
+    }
+
+    pub fn reset(self) -> bool {
+        self.0 & Self::RESET != 0
+    }
+
+    pub fn set_reset(&mut self, value: bool) {
+        set_flag(&mut self.0, Self::RESET, value);
+    }
+}
+
+/// Orange LED register.
+///
+/// - bits 0..6: Brightness (0 to 63)
+/// - bit 7: Blink on sensor error, independent of brightness
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct OrangeLed(pub u8);
+
+impl OrangeLed {
+    /// Maximum brightness.
+    pub const MAX_BRIGHTNESS: u8 = 0x3F;
+
+    const ERROR_STATUS: u8 = 1 << 7;
+
+    /// Create register value, clamping `brightness` to [`Self::MAX_BRIGHTNESS`].
+    pub const fn new(brightness: u8, error_status: bool) -> Self {
+        let brightness = if brightness > Self::MAX_BRIGHTNESS { Self::MAX_BRIGHTNESS } else { brightness };
+
+        OrangeLed(brightness | if error_status { Self::ERROR_STATUS } else { 0 })
+    }
+
+    pub fn brightness(self) -> u8 {
+        self.0 & Self::MAX_BRIGHTNESS
+    }
+
+    pub fn set_brightness(&mut self, brightness: u8) {
+        self.0 = (self.0 & !Self::MAX_BRIGHTNESS) | brightness.min(Self::MAX_BRIGHTNESS);
+    }
+
+    /// LED blinks on sensor error.
+    pub fn error_status(self) -> bool {
+        self.0 & Self::ERROR_STATUS != 0
+    }
+
+    pub fn set_error_status(&mut self, enabled: bool) {
+        set_flag(&mut self.0, Self::ERROR_STATUS, enabled);
+    }
+}
+
+/// UART control register.
+///
+/// - bits 0..3: Baud rate (index into [`BAUD_RATE_MAP`])
+/// - bit 3: CSV output enabled
+/// - bit 4: Debugging enabled
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct UartControl(pub u8);
+
+impl UartControl {
+    const BAUD_RATE_MASK: u8 = 0x07;
+    const CSV_OUTPUT: u8 = 1 << 3;
+    const DEBUGGING: u8 = 1 << 4;
+
+    pub fn baud_rate(self) -> u32 {
+        BAUD_RATE_MAP[(self.0 & Self::BAUD_RATE_MASK) as usize]
+    }
+
+    /// Set the baud rate.
+    ///
+    /// Must be one of [`BAUD_RATE_MAP`].
+    pub fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), NiclaError> {
+        let index = BAUD_RATE_MAP
+            .iter()
+            .position(|&b| b == baud_rate)
+            .ok_or(NiclaError::UnsupportedBaudRate(baud_rate))? as u8;
+
+        self.0 = (self.0 & !Self::BAUD_RATE_MASK) | index;
+
+        Ok(())
+    }
+
+    /// Measurements are streamed as CSV.
+    pub fn csv_output(self) -> bool {
+        self.0 & Self::CSV_OUTPUT != 0
+    }
+
+    pub fn set_csv_output(&mut self, enabled: bool) {
+        set_flag(&mut self.0, Self::CSV_OUTPUT, enabled);
+    }
+
+    /// Debug messages are sent.
+    pub fn debugging(self) -> bool {
+        self.0 & Self::DEBUGGING != 0
+    }
+
+    pub fn set_debugging(&mut self, enabled: bool) {
+        set_flag(&mut self.0, Self::DEBUGGING, enabled);
+    }
+}
+
+/// Sensor values available over both I2C and the UART CSV output.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Measurement {
+    /// HS4001 temperature (degC).
+    pub temperature: f32,
+    /// HS4001 relative humidity (%RH).
+    pub humidity: f32,
+    /// ZMOD4510 EPA air quality index.
+    pub epa_aqi: u16,
+    /// ZMOD4510 fast air quality index.
+    pub fast_aqi: u16,
+    /// ZMOD4510 Ozone (ppb O₃).
+    pub o3: f32,
+    /// ZMOD4510 Nitrogen Dioxide (ppb NO₂).
+    pub no2: f32,
+    /// ZMOD4410 indoor air quality (0 to 5).
+    pub iaq: f32,
+    /// ZMOD4410 relative indoor air quality (0 to 500).
+    pub relative_iaq: f32,
+    /// ZMOD4410 total volitile organic compounds (mg/m³).
+    pub tvoc: f32,
+    /// ZMOD4410 estimated Carbon Dioxide (ppm CO₂).
+    pub eco2: f32,
+    /// ZMOD4410 ethanol (ppm C₂H₆O).
+    pub ethanol: f32,
+}
+
+/// Sample counters of each sensor.
+///
+/// A counter changes whenever its sensor produces a new measurement.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SampleCounters {
+    /// HS4001 temperature/humidity sensor.
+    pub temperature: u32,
+    /// ZMOD4510 outdoor air quality sensor.
+    pub outdoor: u32,
+    /// ZMOD4410 indoor air quality sensor.
+    pub indoor: u32,
+}
+
+impl SampleCounters {
+    pub const fn new() -> Self {
+        SampleCounters {
+            temperature: 0,
+            outdoor: 0,
+            indoor: 0,
+        }
+    }
+}
+
+/// First register read by [`NiclaSenseEnv::read_all`], the last is the ZMOD4410 odor class.
+const SNAPSHOT_FIRST_REGISTER: u8 = 0x14;
+/// Size of the register range read by [`NiclaSenseEnv::read_all`].
+const SNAPSHOT_SIZE: usize = 0xD0 - SNAPSHOT_FIRST_REGISTER as usize + 1;
+
+/// All measurement registers, read in a single transaction.
+///
+/// See [`NiclaSenseEnv::read_all`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Snapshot {
+    pub samples: SampleCounters,
+    /// Values shared with the UART CSV output.
+    ///
+    /// Temperature is -300.0 until the HS4001 has produced a measurement.
+    pub measurement: Measurement,
+    /// ZMOD4510 status.
+    pub outdoor_status: u8,
+    /// ZMOD4510 MOx resistances (Ohm).
+    pub outdoor_rmox: [f32; 13],
+    /// ZMOD4410 status.
+    pub indoor_status: u8,
+    /// ZMOD4410 MOx resistances (Ohm).
+    pub indoor_rmox: [f32; 13],
+    /// ZMOD4410 log10 of CDA resistances.
+    pub indoor_rcda: [f32; 3],
+    /// ZMOD4410 heater resistance.
+    pub indoor_rhtr: f32,
+    /// ZMOD4410 ambient temperature used for compensation (degC).
+    pub indoor_temp: f32,
+    /// ZMOD4410 odor intensity (sulfur odor mode only).
+    pub odor_intensity: f32,
+    /// ZMOD4410 odor class (sulfur odor mode only).
+    pub odor_class: u8,
+}
+
+impl Snapshot {
+    /// The HS4001 has produced a temperature measurement.
+    pub fn temperature_ready(&self) -> bool {
+        self.measurement.temperature > -273.15
+    }
+
+    /// Any sensor has produced a new measurement since `previous`.
+    pub fn has_new_samples(&self, previous: &SampleCounters) -> bool {
+        self.samples != *previous
+    }
+}
+
+impl Measurement {
+    pub const fn new() -> Self {
+        Measurement {
+            temperature: 0.0,
+            humidity: 0.0,
+            epa_aqi: 0,
+            fast_aqi: 0,
+            o3: 0.0,
+            no2: 0.0,
+            iaq: 0.0,
+            relative_iaq: 0.0,
+            tvoc: 0.0,
+            eco2: 0.0,
+            ethanol: 0.0,
+        }
+    }
+}
+
+pub const DEFAULT_DEVICE_ADDRESS: u8 = 0x21;
+
+/// Value of the product ID register.
+pub const PRODUCT_ID: u8 = 0x01;
+
+/// Range of addresses that can be assigned to the board.
+pub const VALID_ADDRESSES: RangeInclusive<u8> = 0x08..=0x77;
+
+/// Supported UART baud rates, indexed by bits 0..3 of [`UartControl`].
+pub const BAUD_RATE_MAP: [u32; 8] = [1200, 2400, 4800, 9600, 19200, 38400, 57600, 115200];
+
+pub struct NiclaSenseEnv<I2C, D> {
+    i2c: I2C,
+    delay: D,
+    address: u8,
 }
 
 #[allow(unused)]
-impl<'a> NiclaSenseEnv<'a> {
-    const DEFAULT_DEVICE_ADDRESS: u8 = 0x21;
-
-    /// Status Register
-    /// - bit 0: Temp/Humidity Enable (1 bit)
-    /// - bit 1..4: Indoor Mode (3 bits)
-    /// - bit 4..6: Outdoor Mode (2 bits)
-    /// - bit 6: Deep Sleep
-    /// - bit 7: Reset
+impl<I2C: I2c, D: DelayNs> NiclaSenseEnv<I2C, D> {
+    /// Status Register (see [`NiclaStatus`])
     const STATUS_REGISTER: u8 = 0x00;
     /// Board Slave Address Register (valid immediately after writing)
     const SLAVE_ADDRESS_REGISTER: u8 = 0x01;
-    /// Board Control Register
+    /// Board Control Register (see [`NiclaControl`])
     const CONTROL_REGISTER: u8 = 0x02;
-    /// Orange LED control
+    /// Orange LED control (see [`OrangeLed`])
     const ORANGE_LED_REGISTER: u8 = 0x03;
     /// RGB Red LED control
     const RGB_RED_REGISTER: u8 = 0x04;
@@ -53,7 +439,7 @@ impl<'a> NiclaSenseEnv<'a> {
     const RGB_GREEN_REGISTER: u8 = 0x06;
     /// RGB Intensity
     const RGB_INTENSITY_REGISTER: u8 = 0x07;
-    /// Board UART Control Register
+    /// Board UART Control Register (see [`UartControl`])
     const UART_CONTROL_REGISTER: u8 = 0x08;
     /// CSV Delimiter character (ASCII)
     const CSV_DELIMITER: u8 = 0x09; // ASCII code
@@ -86,7 +472,7 @@ impl<'a> NiclaSenseEnv<'a> {
     /// ZMOD4410 status
     const ZMOD4410_STATUS_REGISTER: u8 = 0x6B; // u8
     /// ZMOD4410 sample counter
-    const ZMOD4410_SAMPLE_COUNTER_REGISTER: u8 = 0x24; // u32
+    const ZMOD4410_SAMPLE_COUNTER_REGISTER: u8 = 0x6C; // u32
     /// ZMOD4410 IAQ
     const ZMOD4410_IAQ_REGISTER: u8 = 0x70; // f32
     /// ZMOD4410 TVOC (mg/m3)
@@ -109,91 +495,242 @@ impl<'a> NiclaSenseEnv<'a> {
     const ZMOD4410_INTENSITY_REGISTER: u8 = 0xCC; // f32
     /// ZMOD4410 Odor class (1 = sulfur odor, 0 = others)
     const ZMOD4410_ODOR_CLASS_REGISTER: u8 = 0xD0; // u8
+
     /// Persist settings
+    /// - bit 0: Store registers 0x00 ... 0x0B in flash (cleared once complete)
+    /// - bit 1: Restore factory settings (cleared once complete)
     const DEFAULTS_REGISTER: u8 = 0xD4;
 
-    const I2C_TIMEOUT_MS: u64 = 1000;
+    const DEFAULTS_PERSIST: u8 = 1 << 0;
+    const DEFAULTS_RESTORE: u8 = 1 << 1;
 
-    const BAUD_RATE_MAP: [u32; 8] = [1200, 2400, 4800, 9600, 19200, 38400, 57600, 115200];
+    /// Interval between polls of [`Self::DEFAULTS_REGISTER`].
+    const DEFAULTS_POLL_INTERVAL_MS: u32 = 100;
+    /// Polls of [`Self::DEFAULTS_REGISTER`] before giving up.
+    const DEFAULTS_POLL_ATTEMPTS: u32 = 50;
 
-    pub fn new(bus: &'a mut i2c::BusHandle, device: i2c::DeviceAddress) -> Self {
-        NiclaSenseEnv { bus, device }
+    pub fn new(i2c: I2C, delay: D, address: u8) -> Self {
+        NiclaSenseEnv { i2c, delay, address }
     }
 
-    pub fn with_default_addr(bus: &'a mut i2c::BusHandle) -> Self {
-        NiclaSenseEnv { bus, device: i2c::DeviceAddress::new(Self::DEFAULT_DEVICE_ADDRESS) }
+    pub fn with_default_addr(i2c: I2C, delay: D) -> Self {
+        Self::new(i2c, delay, DEFAULT_DEVICE_ADDRESS)
     }
 
-    pub fn is_ready(&mut self) -> bool {
-        let timeout = FuriDuration::from_millis(Self::I2C_TIMEOUT_MS);
+    /// Release the underlying bus and delay.
+    pub fn release(self) -> (I2C, D) {
+        (self.i2c, self.delay)
+    }
 
-        self.bus.is_device_ready(self.device, timeout)
+    pub fn is_ready(&mut self) -> bool {
+        self.i2c.write(self.address, &[]).is_ok()
     }
 
-    pub fn software_revision(&mut self) -> u8 {
-        self.read_u8(Self::SOFTWARE_REVISION_REGISTER).unwrap()
+    pub fn software_revision(&mut self) -> Result<u8, NiclaError> {
+        self.read_u8(Self::SOFTWARE_REVISION_REGISTER)
     }
 
-    pub fn product_id(&mut self) -> u8 {
-        self.read_u8(Self::PRODUCT_ID_REGISTER).unwrap()
+    pub fn product_id(&mut self) -> Result<u8, NiclaError> {
+        self.read_u8(Self::PRODUCT_ID_REGISTER)
     }
 
-    pub fn serial_number(&mut self) -> [u8; 6] {
+    pub fn serial_number(&mut self) -> Result<[u8; 6], NiclaError> {
         let mut buf = [0u8; 6];
-        self.read_exact(Self::SERIAL_NUMBER_REGISTER, &mut buf).ok();
+        self.read_exact(Self::SERIAL_NUMBER_REGISTER, &mut buf)?;
 
-        buf
+        Ok(buf)
     }
 
-    pub fn reset(&mut self) {
-        let status = self.read_u8(Self::STATUS_REGISTER).unwrap();
-        self.write_u8(Self::CONTROL_REGISTER, status | (1 << 7)).ok();
+    /// Change the I2C address of the board.
+    ///
+    /// The new address takes effect immediately and is checked before the settings are optionally stored in flash.
+    /// If the board does not respond at the new address but still does at the old one, this driver continues to use
+    /// the old address. If it responds at neither, [`NiclaError::AddressUnknown`] is returned.
+    pub fn set_device_address(&mut self, address: u8, persist: bool) -> Result<(), NiclaError> {
+        if !VALID_ADDRESSES.contains(&address) {
+            return Err(NiclaError::InvalidAddress(address));
+        }
+
+        self.write_u8(Self::SLAVE_ADDRESS_REGISTER, address)?;
+
+        let previous = self.address;
+        self.address = address;
+
+        if !self.responds_at_own_address() {
+            self.address = previous;
+            if !self.responds_at_own_address() {
+                self.address = address;
+                return Err(NiclaError::AddressUnknown);
+            }
+
+            return Err(NiclaError::NoDevice(address));
+        }
+
+        if persist {
+            self.persist_settings()?;
+        }
+
+        Ok(())
+    }
+
+    /// Check that the board acknowledges the current address and reports it in its address register.
+    fn responds_at_own_address(&mut self) -> bool {
+        self.is_ready() && self.read_u8(Self::SLAVE_ADDRESS_REGISTER) == Ok(self.address)
+    }
+
+    /// Get the status register.
+    pub fn status(&mut self) -> Result<NiclaStatus, NiclaError> {
+        Ok(NiclaStatus(self.read_u8(Self::STATUS_REGISTER)?))
+    }
+
+    /// Write the status register.
+    pub fn set_status(&mut self, status: NiclaStatus) -> Result<(), NiclaError> {
+        self.write_u8(Self::STATUS_REGISTER, status.0)
+    }
+
+    /// Read, modify and write back the status register.
+    pub fn modify_status(&mut self, f: impl FnOnce(&mut NiclaStatus)) -> Result<(), NiclaError> {
+        let mut status = self.status()?;
+        f(&mut status);
+
+        self.set_status(status)
+    }
+
+    /// Get the board control register.
+    pub fn control(&mut self) -> Result<NiclaControl, NiclaError> {
+        Ok(NiclaControl(self.read_u8(Self::CONTROL_REGISTER)?))
+    }
+
+    /// Write the board control register.
+    pub fn set_control(&mut self, control: NiclaControl) -> Result<(), NiclaError> {
+        self.write_u8(Self::CONTROL_REGISTER, control.0)
+    }
+
+    pub fn reset(&mut self) -> Result<(), NiclaError> {
+        let mut control = self.control()?;
+        control.set_reset(true);
+
+        self.set_control(control)
+    }
+
+    pub fn deep_sleep(&mut self) -> Result<(), NiclaError> {
+        let mut control = self.control()?;
+        control.set_deep_sleep(true);
+
+        self.set_control(control)
+    }
+
+    /// Check if the temperature/humidity sensor is enabled.
+    pub fn temperature_enabled(&mut self) -> Result<bool, NiclaError> {
+        Ok(self.status()?.temperature_enabled())
     }
 
-    pub fn deep_sleep(&mut self) {
-        let status = self.read_u8(Self::STATUS_REGISTER).unwrap();
-        self.write_u8(Self::CONTROL_REGISTER, status | (1 << 6)).ok();
+    /// Enable or disable the temperature/humidity sensor.
+    pub fn set_temperature_enabled(&mut self, enabled: bool) -> Result<(), NiclaError> {
+        self.modify_status(|status| status.set_temperature_enabled(enabled))
     }
 
-    // TODO: restore_factory_settings
+    /// Restore the factory settings of board registers 0x00 ... 0x0B and store them in flash.
+    pub fn restore_factory_settings(&mut self) -> Result<(), NiclaError> {
+        self.set_defaults_flag(Self::DEFAULTS_RESTORE)
+    }
 
-    pub fn uart_baud_rate(&mut self) -> u32 {
-        let index = self.read_u8(Self::UART_CONTROL_REGISTER).unwrap() & 0x07;
+    /// Get the UART control register.
+    pub fn uart_control(&mut self) -> Result<UartControl, NiclaError> {
+        Ok(UartControl(self.read_u8(Self::UART_CONTROL_REGISTER)?))
+    }
+
+    /// Write the UART control register.
+    pub fn set_uart_control(&mut self, uart_control: UartControl) -> Result<(), NiclaError> {
+        self.write_u8(Self::UART_CONTROL_REGISTER, uart_control.0)
+    }
 
-        Self::BAUD_RATE_MAP[index as usize]
+    pub fn uart_baud_rate(&mut self) -> Result<u32, NiclaError> {
+        Ok(self.uart_control()?.baud_rate())
     }
 
-    // TODO: set_baud_rate
+    /// Set the UART baud rate.
+    ///
+    /// Must be one of [`BAUD_RATE_MAP`]. If `persist` is set, the settings are also stored in flash.
+    pub fn set_baud_rate(&mut self, baud_rate: u32, persist: bool) -> Result<(), NiclaError> {
+        let current = self.uart_control()?;
+
+        let mut uart_control = current;
+        uart_control.set_baud_rate(baud_rate)?;
+        if uart_control != current {
+            self.set_uart_control(uart_control)?;
+        }
+
+        if persist {
+            self.persist_settings()?;
+        }
+
+        Ok(())
+    }
+
+    /// Check if measurements are streamed as CSV over UART.
+    pub fn csv_output_enabled(&mut self) -> Result<bool, NiclaError> {
+        Ok(self.uart_control()?.csv_output())
+    }
+
+    /// Enable or disable streaming measurements as CSV over UART.
+    ///
+    /// See [`CsvDecoder`] for decoding the stream.
+    pub fn set_csv_output_enabled(&mut self, enabled: bool) -> Result<(), NiclaError> {
+        let mut uart_control = self.uart_control()?;
+        uart_control.set_csv_output(enabled);
+
+        self.set_uart_control(uart_control)
+    }
+
+    /// Get the delimiter used between CSV fields (ASCII).
+    pub fn csv_delimiter(&mut self) -> Result<u8, NiclaError> {
+        self.read_u8(Self::CSV_DELIMITER)
+    }
 
-    /// Set Orange LED register
-    /// - bits 0 to 5: Brightness
-    /// - bit 6: ???
-    /// - bit 7: If set, LED will blink on sensor error independent of brightness setting.
-    pub fn set_orange_led(&mut self, value: u8) {
-        self.write_u8(Self::ORANGE_LED_REGISTER, value).unwrap()
+    /// Set the delimiter used between CSV fields (ASCII).
+    pub fn set_csv_delimiter(&mut self, delimiter: u8) -> Result<(), NiclaError> {
+        self.write_u8(Self::CSV_DELIMITER, delimiter)
     }
 
-    pub fn set_rgb_colour(&mut self, red: u8, green: u8, blue: u8) {
-        self.write_u8(Self::RGB_RED_REGISTER, red).unwrap();
-        self.write_u8(Self::RGB_GREEN_REGISTER, green).unwrap();
-        self.write_u8(Self::RGB_BLUE_REGISTER, blue).unwrap();
+    /// Get the orange LED register.
+    pub fn orange_led(&mut self) -> Result<OrangeLed, NiclaError> {
+        Ok(OrangeLed(self.read_u8(Self::ORANGE_LED_REGISTER)?))
     }
 
-    pub fn set_rgb_intensity(&mut self, value: u8) {
-        self.write_u8(Self::RGB_INTENSITY_REGISTER, value).unwrap()
+    /// Set the orange LED register.
+    pub fn set_orange_led(&mut self, led: OrangeLed) -> Result<(), NiclaError> {
+        self.write_u8(Self::ORANGE_LED_REGISTER, led.0)
+    }
+
+    pub fn set_rgb_colour(&mut self, red: u8, green: u8, blue: u8) -> Result<(), NiclaError> {
+        self.write_u8(Self::RGB_RED_REGISTER, red)?;
+        self.write_u8(Self::RGB_GREEN_REGISTER, green)?;
+        self.write_u8(Self::RGB_BLUE_REGISTER, blue)?;
+
+        Ok(())
+    }
+
+    pub fn set_rgb_intensity(&mut self, value: u8) -> Result<(), NiclaError> {
+        self.write_u8(Self::RGB_INTENSITY_REGISTER, value)
     }
 
     /// Get the temperature in degrees Celsius.
     ///
-    /// A value of -300.0 indicates that the temperature sensor is not ready.
-    pub fn temperature(&mut self) -> f32 {
-        /// 0x00 0x00 0x96 0xc3 = Not ready (-300.0)
-        self.read_f32(Self::TEMPERATURE_REGISTER).unwrap()
+    /// Returns [`NiclaError::NotReady`] if the temperature sensor has not produced a measurement yet.
+    pub fn temperature(&mut self) -> Result<f32, NiclaError> {
+        // 0x00 0x00 0x96 0xc3 = Not ready (-300.0)
+        let temperature = self.read_f32(Self::TEMPERATURE_REGISTER)?;
+        if temperature <= -273.15 {
+            return Err(NiclaError::NotReady);
+        }
+
+        Ok(temperature)
     }
 
     /// Get the relative humidity level (0-100%RH).
-    pub fn humidity(&mut self) -> f32 {
-        self.read_f32(Self::HUMIDITY_REGISTER).unwrap()
+    pub fn humidity(&mut self) -> Result<f32, NiclaError> {
+        self.read_f32(Self::HUMIDITY_REGISTER)
     }
 
     /// Get the mode of the outdoor sensor.
@@ -201,62 +738,52 @@ impl<'a> NiclaSenseEnv<'a> {
     /// - 0: Mode to turn off the sensor and reduce power consumption.
     /// - 1: Cleaning mode to perform a thermal cleaning cycle of the MOx element.
     /// - 2: Mode to measure outdoor air quality.
-    pub fn outdoor_sensor_mode(&mut self) -> OutdoorSensorMode {
-        let status = self.read_u8(Self::STATUS_REGISTER).unwrap();
-        let mode = (status >> 4) & 3;
-
-        match mode {
-            0 => OutdoorSensorMode::Off,
-            1 => OutdoorSensorMode::Cleaning,
-            2 => OutdoorSensorMode::OutdoorAirQuality,
-            _ => panic!("invalid state"),
-        }
-    }
+    pub fn outdoor_sensor_mode(&mut self) -> Result<OutdoorSensorMode, NiclaError> {
+        let status = self.status()?;
 
-    pub fn set_outdoor_sensor_mode(&mut self, mode: OutdoorSensorMode) -> bool {
-        let status = self.read_u8(Self::STATUS_REGISTER).unwrap();
+        status
+            .outdoor_mode()
+            .ok_or(NiclaError::InvalidValue { register: Self::STATUS_REGISTER, value: status.0 })
+    }
 
-        let new_status = (status & !(3 << 4)) | ((mode as u8) << 4);
-        self.write_u8(Self::STATUS_REGISTER, new_status).is_ok()
+    pub fn set_outdoor_sensor_mode(&mut self, mode: OutdoorSensorMode) -> Result<(), NiclaError> {
+        self.modify_status(|status| status.set_outdoor_mode(mode))
     }
 
     /// Retrieves the EPA air quality index. Range is 0 to 500.
     ///
     /// The" EPA AQI" is strictly following the EPA standard and is based on
     /// the 1-hour or 8-hour average of the ozone concentrations (concentration dependent).
-    pub fn outdoor_epa_aqi(&mut self) -> u16 {
-        self.read_u16(Self::ZMOD4510_EPA_AQI_REGISTER).unwrap()
+    pub fn outdoor_epa_aqi(&mut self) -> Result<u16, NiclaError> {
+        self.read_u16(Self::ZMOD4510_EPA_AQI_REGISTER)
     }
 
     /// Get the fast air quality index. Range is 0 to 500.
     ///
     /// As the standard averaging leads to a very slow response, especially during testing and evaluation,
     /// "Fast AQI" provides quicker results with a 1-minute averaging.
-    pub fn outdoor_fast_aqi(&mut self) -> u16 {
-        self.read_u16(Self::ZMOD4510_FAST_AQI_REGISTER).unwrap()
+    pub fn outdoor_fast_aqi(&mut self) -> Result<u16, NiclaError> {
+        self.read_u16(Self::ZMOD4510_FAST_AQI_REGISTER)
     }
 
     /// Get the Ozone (O₃) concentration in ppb.
-    pub fn outdoor_o3(&mut self) -> f32 {
-        self.read_f32(Self::ZMOD4510_O3_REGISTER).unwrap()
+    pub fn outdoor_o3(&mut self) -> Result<f32, NiclaError> {
+        self.read_f32(Self::ZMOD4510_O3_REGISTER)
     }
 
     /// Get the Nitrogen Dioxide (NO₂) concentration in ppb.
-    pub fn outdoor_no2(&mut self) -> f32 {
-        self.read_f32(Self::ZMOD4510_NO2_REGISTER).unwrap()
+    pub fn outdoor_no2(&mut self) -> Result<f32, NiclaError> {
+        self.read_f32(Self::ZMOD4510_NO2_REGISTER)
     }
 
     /// MOx resistance.
-    pub fn outdoor_rmox(&mut self) -> [f32; 13] {
-        let mut buf = [0u8; 4 * 13];
-        self.read_exact(Self::ZMOD4510_RMOX_REGISTER, &mut buf).unwrap();
-
-        let mut values = [0.0; 13];
-        for (n, b) in buf.windows(4).enumerate() {
-            values[n] = f32::from_le_bytes([b[0], b[1], b[2], b[3]]);
-        }
+    pub fn outdoor_rmox(&mut self) -> Result<[f32; 13], NiclaError> {
+        self.read_f32_array(Self::ZMOD4510_RMOX_REGISTER)
+    }
 
-        values
+    /// Get the ZMOD4510 status register.
+    pub fn outdoor_status(&mut self) -> Result<u8, NiclaError> {
+        self.read_u8(Self::ZMOD4510_STATUS_REGISTER)
     }
 
     /// Get the mode of the indoor sensor.
@@ -267,42 +794,36 @@ impl<'a> NiclaSenseEnv<'a> {
     /// - 3: Low power indoor air quality mode with lower accuracy.
     /// - 4: Public Building Air Quality mode.
     /// - 5: Mode to detect sulfur odor.
-    pub fn indoor_sensor_mode(&mut self) -> IndoorSensorMode {
-        let status = self.read_u8(Self::STATUS_REGISTER).unwrap();
-        let mode = (status >> 1) & 7;
+    pub fn indoor_sensor_mode(&mut self) -> Result<IndoorSensorMode, NiclaError> {
+        let status = self.status()?;
 
-        use IndoorSensorMode::*;
-        match mode {
-            0 => Off,
-            1 => Cleaning,
-            2 => IndoorAirQuality,
-            3 => IndoorAirQualityLowPower,
-            4 => PublicBuildingAirQuality,
-            5 => Sulfur,
-            _ => panic!("invalid state"),
-        }
+        status
+            .indoor_mode()
+            .ok_or(NiclaError::InvalidValue { register: Self::STATUS_REGISTER, value: status.0 })
     }
 
-    pub fn set_indoor_sensor_mode(&mut self, mode: IndoorSensorMode) -> bool {
-        let status = self.read_u8(Self::STATUS_REGISTER).unwrap();
-        let new_status = (status & !(7 << 1)) | ((mode as u8) << 1);
+    pub fn set_indoor_sensor_mode(&mut self, mode: IndoorSensorMode) -> Result<(), NiclaError> {
+        self.modify_status(|status| status.set_indoor_mode(mode))
+    }
 
-        self.write_u8(Self::STATUS_REGISTER, new_status).is_ok()
+    /// Get the ZMOD4410 status register.
+    pub fn indoor_status(&mut self) -> Result<u8, NiclaError> {
+        self.read_u8(Self::ZMOD4410_STATUS_REGISTER)
     }
 
     /// Get the indoor air quality in range 0 to 5 where 0 is the best air quality and 5 is the worst.
-    pub fn indoor_iqa(&mut self) -> f32 {
-        self.read_f32(Self::ZMOD4410_IAQ_REGISTER).unwrap()
+    pub fn indoor_iqa(&mut self) -> Result<f32, NiclaError> {
+        self.read_f32(Self::ZMOD4410_IAQ_REGISTER)
     }
 
     /// Get the total volitile organic compounds in mg/m³.
-    pub fn indoor_total_voc(&mut self) -> f32 {
-        self.read_f32(Self::ZMOD4410_TVOC_REGISTER).unwrap()
+    pub fn indoor_total_voc(&mut self) -> Result<f32, NiclaError> {
+        self.read_f32(Self::ZMOD4410_TVOC_REGISTER)
     }
 
     /// Get the estimated Carbon Dioxide (CO₂) concentration in ppm.
-    pub fn indoor_estimated_co2(&mut self) -> f32 {
-        self.read_f32(Self::ZMOD4410_ECO2_REGISTER).unwrap()
+    pub fn indoor_estimated_co2(&mut self) -> Result<f32, NiclaError> {
+        self.read_f32(Self::ZMOD4410_ECO2_REGISTER)
     }
 
     /// Get the relative indoor air quality index (0 to 500) over a 24 hour period.
@@ -311,55 +832,39 @@ impl<'a> NiclaSenseEnv<'a> {
     /// - Below 100: Improvement in air quality
     /// - 100: No change in air quality
     /// - Over 100: Degregation in air qualiuty
-    pub fn indoor_relative_iqa(&mut self) -> f32 {
-        self.read_f32(Self::ZMOD4410_REL_IAQ_REGISTER).unwrap()
+    pub fn indoor_relative_iqa(&mut self) -> Result<f32, NiclaError> {
+        self.read_f32(Self::ZMOD4410_REL_IAQ_REGISTER)
     }
 
     /// Get the ethanol (EthOH) concentration in ppm.
-    pub fn indoor_ethanol(&mut self) -> f32 {
-        self.read_f32(Self::ZMOD4410_ETOH_REGISTER).unwrap()
+    pub fn indoor_ethanol(&mut self) -> Result<f32, NiclaError> {
+        self.read_f32(Self::ZMOD4410_ETOH_REGISTER)
     }
 
     /// MOx resistances.
-    pub fn indoor_rmox(&mut self) -> [f32; 13] {
-        let mut buf = [0u8; 4 * 13];
-        self.read_exact(Self::ZMOD4410_RMOX_REGISTER, &mut buf).unwrap();
-
-        let mut values = [0.0; 13];
-        for (n, b) in buf.windows(4).enumerate() {
-            values[n] = f32::from_le_bytes([b[0], b[1], b[2], b[3]]);
-        }
-
-        values
+    pub fn indoor_rmox(&mut self) -> Result<[f32; 13], NiclaError> {
+        self.read_f32_array(Self::ZMOD4410_RMOX_REGISTER)
     }
 
     /// log10 of CDA resistances.
-    pub fn indoor_rcda(&mut self) -> [f32; 3] {
-        let mut buf = [0u8; 4 * 3];
-        self.read_exact(Self::ZMOD4410_RCDA_REGISTER, &mut buf).unwrap();
-
-        let mut values = [0.0; 3];
-        for (n, b) in buf.windows(4).enumerate() {
-            values[n] = f32::from_le_bytes([b[0], b[1], b[2], b[3]]);
-        }
-
-        values
+    pub fn indoor_rcda(&mut self) -> Result<[f32; 3], NiclaError> {
+        self.read_f32_array(Self::ZMOD4410_RCDA_REGISTER)
     }
 
     /// Heater resistance.
-    pub fn indoor_rhtr(&mut self) -> f32 {
-        self.read_f32(Self::ZMOD4410_RHTR_REGISTER).unwrap()
+    pub fn indoor_rhtr(&mut self) -> Result<f32, NiclaError> {
+        self.read_f32(Self::ZMOD4410_RHTR_REGISTER)
     }
 
     /// Ambient temperature (degC).
-    pub fn indoor_temp(&mut self) -> f32 {
-        self.read_f32(Self::ZMOD4410_TEMP_REGISTER).unwrap()
+    pub fn indoor_temp(&mut self) -> Result<f32, NiclaError> {
+        self.read_f32(Self::ZMOD4410_TEMP_REGISTER)
     }
 
     /// Get the odor intensity.
     /// Only for Sulphur Odor mode.
-    pub fn indoor_odor_intensity(&mut self) -> f32 {
-        self.read_f32(Self::ZMOD4410_INTENSITY_REGISTER).unwrap()
+    pub fn indoor_odor_intensity(&mut self) -> Result<f32, NiclaError> {
+        self.read_f32(Self::ZMOD4410_INTENSITY_REGISTER)
     }
 
     /// Get the odor class.
@@ -367,8 +872,91 @@ impl<'a> NiclaSenseEnv<'a> {
     ///
     /// - 1: "sulphur" (sulfur-based)
     /// - 0: "acceptable" (organic-based)
-    pub fn indoor_odor_class(&mut self) -> u8 {
-        self.read_u8(Self::ZMOD4410_ODOR_CLASS_REGISTER).unwrap()
+    pub fn indoor_odor_class(&mut self) -> Result<u8, NiclaError> {
+        self.read_u8(Self::ZMOD4410_ODOR_CLASS_REGISTER)
+    }
+
+    /// Read the values shared with the UART CSV output.
+    ///
+    /// Returns [`NiclaError::NotReady`] if the temperature sensor has not produced a measurement yet.
+    pub fn measurement(&mut self) -> Result<Measurement, NiclaError> {
+        let snapshot = self.read_all()?;
+        if !snapshot.temperature_ready() {
+            return Err(NiclaError::NotReady);
+        }
+
+        Ok(snapshot.measurement)
+    }
+
+    /// Read all measurement registers (`0x14` to `0xD0`) in a single transaction.
+    ///
+    /// Unlike the individual getters, all values are guaranteed to come from the same moment.
+    pub fn read_all(&mut self) -> Result<Snapshot, NiclaError> {
+        let mut buf = [0u8; SNAPSHOT_SIZE];
+        self.read_exact(SNAPSHOT_FIRST_REGISTER, &mut buf)?;
+
+        Ok(Self::decode_snapshot(&buf))
+    }
+
+    fn decode_snapshot(buf: &[u8; SNAPSHOT_SIZE]) -> Snapshot {
+        fn bytes<const N: usize>(buf: &[u8], reg_addr: u8) -> [u8; N] {
+            let offset = (reg_addr - SNAPSHOT_FIRST_REGISTER) as usize;
+
+            let mut bytes = [0u8; N];
+            bytes.copy_from_slice(&buf[offset..offset + N]);
+
+            bytes
+        }
+
+        let u8_at = |reg_addr| u8::from_le_bytes(bytes(buf, reg_addr));
+        let u16_at = |reg_addr| u16::from_le_bytes(bytes(buf, reg_addr));
+        let u32_at = |reg_addr| u32::from_le_bytes(bytes(buf, reg_addr));
+        let f32_at = |reg_addr| f32::from_le_bytes(bytes(buf, reg_addr));
+
+        let mut outdoor_rmox = [0.0; 13];
+        for (n, value) in outdoor_rmox.iter_mut().enumerate() {
+            *value = f32_at(Self::ZMOD4510_RMOX_REGISTER + 4 * n as u8);
+        }
+
+        let mut indoor_rmox = [0.0; 13];
+        for (n, value) in indoor_rmox.iter_mut().enumerate() {
+            *value = f32_at(Self::ZMOD4410_RMOX_REGISTER + 4 * n as u8);
+        }
+
+        let mut indoor_rcda = [0.0; 3];
+        for (n, value) in indoor_rcda.iter_mut().enumerate() {
+            *value = f32_at(Self::ZMOD4410_RCDA_REGISTER + 4 * n as u8);
+        }
+
+        Snapshot {
+            samples: SampleCounters {
+                temperature: u32_at(Self::SAMPLE_COUNTER_REGISTER),
+                outdoor: u32_at(Self::ZMOD4510_SAMPLE_COUNTER_REGISTER),
+                indoor: u32_at(Self::ZMOD4410_SAMPLE_COUNTER_REGISTER),
+            },
+            measurement: Measurement {
+                temperature: f32_at(Self::TEMPERATURE_REGISTER),
+                humidity: f32_at(Self::HUMIDITY_REGISTER),
+                epa_aqi: u16_at(Self::ZMOD4510_EPA_AQI_REGISTER),
+                fast_aqi: u16_at(Self::ZMOD4510_FAST_AQI_REGISTER),
+                o3: f32_at(Self::ZMOD4510_O3_REGISTER),
+                no2: f32_at(Self::ZMOD4510_NO2_REGISTER),
+                iaq: f32_at(Self::ZMOD4410_IAQ_REGISTER),
+                relative_iaq: f32_at(Self::ZMOD4410_REL_IAQ_REGISTER),
+                tvoc: f32_at(Self::ZMOD4410_TVOC_REGISTER),
+                eco2: f32_at(Self::ZMOD4410_ECO2_REGISTER),
+                ethanol: f32_at(Self::ZMOD4410_ETOH_REGISTER),
+            },
+            outdoor_status: u8_at(Self::ZMOD4510_STATUS_REGISTER),
+            outdoor_rmox,
+            indoor_status: u8_at(Self::ZMOD4410_STATUS_REGISTER),
+            indoor_rmox,
+            indoor_rcda,
+            indoor_rhtr: f32_at(Self::ZMOD4410_RHTR_REGISTER),
+            indoor_temp: f32_at(Self::ZMOD4410_TEMP_REGISTER),
+            odor_intensity: f32_at(Self::ZMOD4410_INTENSITY_REGISTER),
+            odor_class: u8_at(Self::ZMOD4410_ODOR_CLASS_REGISTER),
+        }
     }
 
     /// Writes the current configuration to flash memory.
@@ -386,41 +974,416 @@ impl<'a> NiclaSenseEnv<'a> {
     /// - Orange LED error status enabled
     /// - RGB LED brightness
     /// - RGB LED color
-    pub fn persist_settings(&mut self) -> u8 {
-        todo!()
+    pub fn persist_settings(&mut self) -> Result<(), NiclaError> {
+        self.set_defaults_flag(Self::DEFAULTS_PERSIST)
+    }
+
+    /// Set `flag` in [`Self::DEFAULTS_REGISTER`] and wait for the board to clear it.
+    fn set_defaults_flag(&mut self, flag: u8) -> Result<(), NiclaError> {
+        let defaults = self.read_u8(Self::DEFAULTS_REGISTER)?;
+        self.write_u8(Self::DEFAULTS_REGISTER, defaults | flag)?;
+
+        for _ in 0..Self::DEFAULTS_POLL_ATTEMPTS {
+            self.delay.delay_ms(Self::DEFAULTS_POLL_INTERVAL_MS);
+
+            if self.read_u8(Self::DEFAULTS_REGISTER)? & flag == 0 {
+                return Ok(());
+            }
+        }
+
+        Err(NiclaError::Timeout)
     }
 
-    fn write_u8(&mut self, reg_addr: u8, data: u8) -> Result<(), i2c::Error> {
-        self.bus.write_u8(self.device, reg_addr, data, FuriDuration::from_millis(Self::I2C_TIMEOUT_MS))
+    fn write_u8(&mut self, reg_addr: u8, data: u8) -> Result<(), NiclaError> {
+        self.i2c.write(self.address, &[reg_addr, data]).map_err(NiclaError::i2c)
     }
 
-    fn read_u8(&mut self, reg_addr: u8) -> Result<u8, i2c::Error> {
-        self.bus.read_u8(self.device, reg_addr, FuriDuration::from_millis(Self::I2C_TIMEOUT_MS))
+    fn read_u8(&mut self, reg_addr: u8) -> Result<u8, NiclaError> {
+        let mut buf = [0u8; 1];
+        self.read_exact(reg_addr, &mut buf)?;
+
+        Ok(buf[0])
     }
 
-    fn read_u16(&mut self, reg_addr: u8) -> Result<u16, i2c::Error> {
+    fn read_u16(&mut self, reg_addr: u8) -> Result<u16, NiclaError> {
         let mut buf = [0u8; 2];
         self.read_exact(reg_addr, &mut buf)?;
 
         Ok(u16::from_le_bytes(buf))
     }
 
-    fn read_u32(&mut self, reg_addr: u8) -> Result<u32, i2c::Error> {
+    fn read_u32(&mut self, reg_addr: u8) -> Result<u32, NiclaError> {
         let mut buf = [0u8; 4];
         self.read_exact(reg_addr, &mut buf)?;
 
         Ok(u32::from_le_bytes(buf))
     }
 
-    fn read_f32(&mut self, reg_addr: u8) -> Result<f32, i2c::Error> {
+    fn read_f32(&mut self, reg_addr: u8) -> Result<f32, NiclaError> {
         let mut buf = [0u8; 4];
         self.read_exact(reg_addr, &mut buf)?;
 
         Ok(f32::from_le_bytes(buf))
     }
 
-    fn read_exact(&mut self, reg_addr: u8, buf: &mut [u8]) -> Result<(), i2c::Error> {
-        self.bus.read_exact(self.device, reg_addr, buf, FuriDuration::from_millis(Self::I2C_TIMEOUT_MS))
+    fn read_f32_array<const N: usize>(&mut self, reg_addr: u8) -> Result<[f32; N], NiclaError> {
+        let mut buf = [[0u8; 4]; N];
+        self.read_exact(reg_addr, buf.as_flattened_mut())?;
+
+        Ok(buf.map(f32::from_le_bytes))
     }
 
+    fn read_exact(&mut self, reg_addr: u8, buf: &mut [u8]) -> Result<(), NiclaError> {
+        self.i2c.write_read(self.address, &[reg_addr], buf).map_err(NiclaError::i2c)
+    }
+}
+
+impl<I2C: I2c, D: DelayNs> HumiditySource for NiclaSenseEnv<I2C, D> {
+    fn temperature_humidity(&mut self) -> Option<(f32, f32)> {
+        if !self.is_ready() {
+            return None;
+        }
+
+        let temperature = self.temperature().ok()?;
+        let humidity = self.humidity().ok()?;
+
+        Some((temperature, humidity))
+    }
+}
+
+/// UART CSV decoding errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsvError {
+    /// Line is longer than [`CSV_LINE_SIZE`].
+    LineTooLong,
+    /// Line is not valid UTF-8.
+    InvalidUtf8,
+    /// Line has fewer than [`CsvDecoder::COLUMNS`] columns.
+    MissingColumns(usize),
+    /// Column could not be parsed as a number.
+    InvalidColumn(usize),
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::LineTooLong => f.write_str("line too long"),
+            CsvError::InvalidUtf8 => f.write_str("invalid UTF-8"),
+            CsvError::MissingColumns(count) => write!(f, "only {count} columns"),
+            CsvError::InvalidColumn(column) => write!(f, "invalid value in column {column}"),
+        }
+    }
+}
+
+/// Maximum length of a CSV line.
+pub const CSV_LINE_SIZE: usize = 1024;
+
+/// Streaming decoder for the UART CSV output.
+///
+/// The board sends one line per measurement. Column order follows the Arduino_NiclaSenseEnv library:
+/// HS4001 sample counter, temperature and humidity; ZMOD4510 status, sample counter, EPA AQI, fast AQI,
+/// O₃, NO₂ and Rmox\[0..13\]; ZMOD4410 status, sample counter, EtOH, TVOC, eCO₂, relative IAQ, IAQ,
+/// Rmox\[0..13\], log10 Rcda\[0..3\], Rhtr, temperature, odor intensity and odor class.
+pub struct CsvDecoder {
+    line: heapless::Vec<u8, CSV_LINE_SIZE>,
+    delimiter: u8,
+    overflow: bool,
+}
+
+impl CsvDecoder {
+    /// Number of columns in a line.
+    pub const COLUMNS: usize = 49;
+
+    const COLUMN_TEMPERATURE: usize = 1;
+    const COLUMN_HUMIDITY: usize = 2;
+    const COLUMN_EPA_AQI: usize = 5;
+    const COLUMN_FAST_AQI: usize = 6;
+    const COLUMN_O3: usize = 7;
+    const COLUMN_NO2: usize = 8;
+    const COLUMN_ETHANOL: usize = 24;
+    const COLUMN_TVOC: usize = 25;
+    const COLUMN_ECO2: usize = 26;
+    const COLUMN_RELATIVE_IAQ: usize = 27;
+    const COLUMN_IAQ: usize = 28;
+
+    /// Create a decoder for lines using `delimiter` between fields.
+    pub const fn new(delimiter: u8) -> Self {
+        CsvDecoder { line: heapless::Vec::new(), delimiter, overflow: false }
+    }
+
+    /// Change the delimiter between fields.
+    pub fn set_delimiter(&mut self, delimiter: u8) {
+        self.delimiter = delimiter;
+    }
+
+    /// Discard any partially received line.
+    pub fn reset(&mut self) {
+        self.line.clear();
+        self.overflow = false;
+    }
+
+    /// Decode all lines completed by `data`.
+    pub fn decode<'a>(&'a mut self, data: &'a [u8]) -> impl Iterator<Item = Result<Measurement, CsvError>> + 'a {
+        data.iter().filter_map(move |&byte| self.push(byte))
+    }
+
+    /// Push a single byte into the decoder.
+    ///
+    /// Returns the decoded measurement once a complete line has been received.
+    pub fn push(&mut self, byte: u8) -> Option<Result<Measurement, CsvError>> {
+        match byte {
+            b'\r' => None,
+            b'\n' if self.line.is_empty() && !self.overflow => None,
+            b'\n' => {
+                let result = if self.overflow {
+                    Err(CsvError::LineTooLong)
+                } else {
+                    self.parse_line()
+                };
+                self.reset();
+
+                Some(result)
+            },
+            _ => {
+                if self.line.push(byte).is_err() {
+                    self.overflow = true;
+                }
+
+                None
+            },
+        }
+    }
+
+    fn parse_line(&self) -> Result<Measurement, CsvError> {
+        let line = core::str::from_utf8(&self.line).map_err(|_| CsvError::InvalidUtf8)?;
+
+        let mut columns: [&str; Self::COLUMNS] = [""; Self::COLUMNS];
+        let mut count = 0;
+        for (column, field) in columns.iter_mut().zip(line.split(self.delimiter as char)) {
+            *column = field.trim();
+            count += 1;
+        }
+
+        if count < Self::COLUMNS {
+            return Err(CsvError::MissingColumns(count));
+        }
+
+        let f32_column = |n: usize| columns[n].parse::<f32>().map_err(|_| CsvError::InvalidColumn(n));
+        let u16_column = |n: usize| columns[n].parse::<u16>().map_err(|_| CsvError::InvalidColumn(n));
+
+        Ok(Measurement {
+            temperature: f32_column(Self::COLUMN_TEMPERATURE)?,
+            humidity: f32_column(Self::COLUMN_HUMIDITY)?,
+            epa_aqi: u16_column(Self::COLUMN_EPA_AQI)?,
+            fast_aqi: u16_column(Self::COLUMN_FAST_AQI)?,
+            o3: f32_column(Self::COLUMN_O3)?,
+            no2: f32_column(Self::COLUMN_NO2)?,
+            iaq: f32_column(Self::COLUMN_IAQ)?,
+            relative_iaq: f32_column(Self::COLUMN_RELATIVE_IAQ)?,
+            tvoc: f32_column(Self::COLUMN_TVOC)?,
+            eco2: f32_column(Self::COLUMN_ECO2)?,
+            ethanol: f32_column(Self::COLUMN_ETHANOL)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use embedded_hal::i2c::ErrorKind;
+    use embedded_hal_mock::eh1::delay::NoopDelay;
+    use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction};
+    use std::vec;
+
+    use super::*;
+
+    /// Build a CSV line where column `n` has the value `n`, except for `invalid` which is not a number.
+    fn test_line(delimiter: char, invalid: Option<usize>) -> heapless::String<256> {
+        let mut line = heapless::String::new();
+        for n in 0..CsvDecoder::COLUMNS {
+            if n > 0 {
+                line.push(delimiter).unwrap();
+            }
+
+            if invalid == Some(n) {
+                line.push('x').unwrap();
+            } else {
+                fmt::write(&mut line, format_args!("{n}")).unwrap();
+            }
+        }
+        line.push_str("\r\n").unwrap();
+
+        line
+    }
+
+    #[test]
+    fn test_status() {
+        let mut status = NiclaStatus(0b0010_0101);
+        assert!(status.temperature_enabled());
+        assert_eq!(status.indoor_mode(), Some(IndoorSensorMode::IndoorAirQuality));
+        assert_eq!(status.outdoor_mode(), Some(OutdoorSensorMode::OutdoorAirQuality));
+        assert!(!status.deep_sleep());
+
+        status.set_temperature_enabled(false);
+        status.set_indoor_mode(IndoorSensorMode::Sulfur);
+        status.set_outdoor_mode(OutdoorSensorMode::Cleaning);
+        assert_eq!(status, NiclaStatus(0b0001_1010));
+
+        assert_eq!(NiclaStatus(0b0000_1110).indoor_mode(), None);
+        assert_eq!(NiclaStatus(0b0011_0000).outdoor_mode(), None);
+    }
+
+    #[test]
+    fn test_orange_led() {
+        assert_eq!(OrangeLed::new(100, true), OrangeLed(0xBF));
+
+        let mut led = OrangeLed(0x80);
+        led.set_brightness(10);
+        assert_eq!(led.brightness(), 10);
+        assert!(led.error_status());
+    }
+
+    #[test]
+    fn test_uart_control() {
+        let mut uart_control = UartControl(0b0001_0011);
+        assert_eq!(uart_control.baud_rate(), 9600);
+        assert!(!uart_control.csv_output());
+        assert!(uart_control.debugging());
+
+        uart_control.set_csv_output(true);
+        assert_eq!(uart_control.set_baud_rate(115200), Ok(()));
+        assert_eq!(uart_control, UartControl(0b0001_1111));
+
+        assert_eq!(uart_control.set_baud_rate(1000), Err(NiclaError::UnsupportedBaudRate(1000)));
+    }
+
+    #[test]
+    fn test_decode_snapshot() {
+        fn put(buf: &mut [u8], reg_addr: u8, bytes: &[u8]) {
+            let offset = (reg_addr - 0x14) as usize;
+            buf[offset..offset + bytes.len()].copy_from_slice(bytes);
+        }
+
+        let mut buf = [0u8; SNAPSHOT_SIZE];
+        put(&mut buf, 0x14, &7u32.to_le_bytes());
+        put(&mut buf, 0x18, &21.5f32.to_le_bytes());
+        put(&mut buf, 0x24, &8u32.to_le_bytes());
+        put(&mut buf, 0x28, &42u16.to_le_bytes());
+        put(&mut buf, 0x34 + 4 * 12, &123.0f32.to_le_bytes());
+        put(&mut buf, 0x6C, &9u32.to_le_bytes());
+        put(&mut buf, 0x80, &0.5f32.to_le_bytes());
+        put(&mut buf, 0xB8 + 4 * 2, &3.0f32.to_le_bytes());
+        put(&mut buf, 0xD0, &[1]);
+
+        let snapshot = NiclaSenseEnv::<I2cMock, NoopDelay>::decode_snapshot(&buf);
+        assert_eq!(snapshot.samples, SampleCounters { temperature: 7, outdoor: 8, indoor: 9 });
+        assert_eq!(snapshot.measurement.temperature, 21.5);
+        assert_eq!(snapshot.measurement.epa_aqi, 42);
+        assert_eq!(snapshot.measurement.ethanol, 0.5);
+        assert_eq!(snapshot.outdoor_rmox[12], 123.0);
+        assert_eq!(snapshot.indoor_rcda[2], 3.0);
+        assert_eq!(snapshot.odor_class, 1);
+        assert!(snapshot.temperature_ready());
+        assert!(snapshot.has_new_samples(&SampleCounters::new()));
+    }
+
+    #[test]
+    fn test_set_device_address() {
+        let expectations = [
+            // Moved to 0x30 and confirmed
+            Transaction::write(0x21, vec![0x01, 0x30]),
+            Transaction::write(0x30, vec![]),
+            Transaction::write_read(0x30, vec![0x01], vec![0x30]),
+            // Moved to 0x40 but no response
+            Transaction::write(0x30, vec![0x01, 0x40]),
+            Transaction::write(0x40, vec![]).with_error(ErrorKind::NoAcknowledge(i2c::NoAcknowledgeSource::Address)),
+            Transaction::write(0x30, vec![]),
+            Transaction::write_read(0x30, vec![0x01], vec![0x30]),
+            Transaction::write_read(0x30, vec![0x02], vec![0x00]),
+            // Moved to 0x41 but responds at neither address
+            Transaction::write(0x30, vec![0x01, 0x41]),
+            Transaction::write(0x41, vec![]).with_error(ErrorKind::NoAcknowledge(i2c::NoAcknowledgeSource::Address)),
+            Transaction::write(0x30, vec![]).with_error(ErrorKind::NoAcknowledge(i2c::NoAcknowledgeSource::Address)),
+        ];
+        let mut board = NiclaSenseEnv::with_default_addr(I2cMock::new(&expectations), NoopDelay);
+
+        assert_eq!(board.set_device_address(0x30, false), Ok(()));
+        assert_eq!(board.set_device_address(0x40, false), Err(NiclaError::NoDevice(0x40)));
+        assert_eq!(board.set_device_address(0x78, false), Err(NiclaError::InvalidAddress(0x78)));
+
+        // Driver still uses the previous address
+        assert_eq!(board.control().map(|control| control.0), Ok(0x00));
+
+        assert_eq!(board.set_device_address(0x41, false), Err(NiclaError::AddressUnknown));
+
+        board.release().0.done();
+    }
+
+    #[test]
+    fn test_csv_decode() {
+        let line = test_line(',', None);
+        let mut decoder = CsvDecoder::new(b',');
+
+        let results: heapless::Vec<_, 4> = decoder.decode(line.as_bytes()).collect();
+        assert_eq!(results.len(), 1);
+
+        let measurement = results[0].unwrap();
+        assert_eq!(measurement.temperature, 1.0);
+        assert_eq!(measurement.humidity, 2.0);
+        assert_eq!(measurement.epa_aqi, 5);
+        assert_eq!(measurement.fast_aqi, 6);
+        assert_eq!(measurement.no2, 8.0);
+        assert_eq!(measurement.ethanol, 24.0);
+        assert_eq!(measurement.iaq, 28.0);
+    }
+
+    #[test]
+    fn test_csv_delimiter() {
+        let line = test_line(';', None);
+
+        let mut decoder = CsvDecoder::new(b',');
+        let results: heapless::Vec<_, 4> = decoder.decode(line.as_bytes()).collect();
+        assert_eq!(&results[..], &[Err(CsvError::MissingColumns(1))]);
+
+        decoder.set_delimiter(b';');
+        let results: heapless::Vec<_, 4> = decoder.decode(line.as_bytes()).collect();
+        assert!(results[0].is_ok());
+    }
+
+    #[test]
+    fn test_csv_split_lines() {
+        let line = test_line(',', None);
+        let mut decoder = CsvDecoder::new(b',');
+
+        let mut results: heapless::Vec<_, 4> = heapless::Vec::new();
+        for chunk in line.as_bytes().chunks(7) {
+            results.extend(decoder.decode(chunk));
+        }
+
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_ok());
+    }
+
+    #[test]
+    fn test_csv_invalid_column() {
+        let line = test_line(',', Some(CsvDecoder::COLUMN_HUMIDITY));
+        let mut decoder = CsvDecoder::new(b',');
+
+        let results: heapless::Vec<_, 4> = decoder.decode(line.as_bytes()).collect();
+        assert_eq!(&results[..], &[Err(CsvError::InvalidColumn(CsvDecoder::COLUMN_HUMIDITY))]);
+    }
+
+    #[test]
+    fn test_csv_line_too_long() {
+        let mut decoder = CsvDecoder::new(b',');
+
+        assert!(decoder.decode(&[b'0'; CSV_LINE_SIZE + 1]).next().is_none());
+        assert_eq!(decoder.push(b'\n'), Some(Err(CsvError::LineTooLong)));
+
+        // Decoder recovers on the next line
+        let line = test_line(',', None);
+        let results: heapless::Vec<_, 4> = decoder.decode(line.as_bytes()).collect();
+        assert!(results[0].is_ok());
+    }
 }
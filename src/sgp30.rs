@@ -0,0 +1,365 @@
+//! Driver for the [Sensirion SGP30](https://sensirion.com/products/catalog/SGP30)
+//! multi-pixel gas sensor.
+
+use core::fmt;
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::{self, I2c};
+
+use crate::crc::sensirion_crc8;
+use crate::humidity::absolute_humidity;
+
+/// SGP30 errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// I2C transfer failed.
+    I2c(i2c::ErrorKind),
+    /// Received word did not match its CRC.
+    Crc,
+    /// Sensor has not completed initialization.
+    NotReady,
+}
+
+impl Error {
+    fn i2c(err: impl i2c::Error) -> Self {
+        Error::I2c(err.kind())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::I2c(err) => write!(f, "I2C error: {err}"),
+            Error::Crc => f.write_str("CRC mismatch"),
+            Error::NotReady => f.write_str("not ready"),
+        }
+    }
+}
+
+/// Measurement state of the sensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    /// `Init_air_quality` has not been sent.
+    Uninitialized,
+    /// Initialization phase after `Init_air_quality`.
+    ///
+    /// For the first 15 seconds the sensor returns fixed values of 400 ppm CO₂eq and 0 ppb TVOC.
+    Initializing { samples: u32 },
+    /// Sensor is returning valid measurements.
+    Measuring,
+}
+
+/// Air quality measurement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct IaqMeasurement {
+    /// Carbon Dioxide equivalent (ppm).
+    pub co2_eq: u16,
+    /// Total Volitile Organic Compounds (ppb).
+    pub tvoc: u16,
+}
+
+/// IAQ algorithm baseline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Baseline {
+    pub co2_eq: u16,
+    pub tvoc: u16,
+}
+
+/// Raw sensor signals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RawSignals {
+    /// Hydrogen (H₂) signal (ticks).
+    pub h2: u16,
+    /// Ethanol (C₂H₆O) signal (ticks).
+    pub ethanol: u16,
+}
+
+/// Convert absolute humidity (g/m³) to the 8.8 fixed-point format used by [`Sgp30::set_abs_humidity`].
+///
+/// Values are clamped to the supported range (1/256 to 255.996 g/m³),
+/// since a value of 0 would disable humidity compensation.
+pub fn abs_humidity_to_fixed(value: f32) -> u16 {
+    (value * 256.0).clamp(1.0, u16::MAX as f32) as u16
+}
+
+/// Product type and version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeatureSet(pub u16);
+
+impl FeatureSet {
+    /// Product type (0 for SGP30).
+    pub fn product_type(self) -> u8 {
+        (self.0 >> 12) as u8
+    }
+
+    /// Product feature set version.
+    pub fn product_version(self) -> u8 {
+        self.0 as u8
+    }
+}
+
+/// Expected result of `Measure_test`.
+pub const SELF_TEST_OK: u16 = 0xD400;
+
+pub struct Sgp30<I2C, D> {
+    i2c: I2C,
+    delay: D,
+    address: u8,
+    state: State,
+}
+
+#[allow(unused)]
+impl<I2C: I2c, D: DelayNs> Sgp30<I2C, D> {
+    const DEFAULT_DEVICE_ADDRESS: u8 = 0x58;
+
+    /// Number of 1 Hz measurements in the initialization phase.
+    const INIT_SAMPLES: u32 = 15;
+
+    const IAQ_INIT: [u8; 2] = [0x20, 0x03];
+    const MEASURE_IAQ: [u8; 2] = [0x20, 0x08];
+    const GET_IAQ_BASELINE: [u8; 2] = [0x20, 0x15];
+    const SET_IAQ_BASELINE: [u8; 2] = [0x20, 0x1E];
+    const SET_ABS_HUMIDITY: [u8; 2] = [0x20, 0x61];
+    const MEASURE_TEST: [u8; 2] = [0x20, 0x32];
+    const GET_FEATURE_SET: [u8; 2] = [0x20, 0x2F];
+    const MEASURE_RAW: [u8; 2] = [0x20, 0x50];
+    const GET_TVOC_INCEPTIVE_BASELINE: [u8; 2] = [0x20, 0xB3];
+    const SET_TVOC_BASELINE: [u8; 2] = [0x20, 0x77];
+    const GET_SERIAL_ID: [u8; 2] = [0x36, 0x82];
+
+    pub fn new(i2c: I2C, delay: D, address: u8) -> Self {
+        Sgp30 { i2c, delay, address, state: State::Uninitialized }
+    }
+
+    pub fn with_default_addr(i2c: I2C, delay: D) -> Self {
+        Self::new(i2c, delay, Self::DEFAULT_DEVICE_ADDRESS)
+    }
+
+    pub fn is_ready(&mut self) -> bool {
+        self.i2c.write(self.address, &[]).is_ok()
+    }
+
+    /// Borrow the underlying bus (e.g. to talk to a companion humidity sensor).
+    pub fn bus(&mut self) -> &mut I2C {
+        &mut self.i2c
+    }
+
+    /// Release the underlying bus and delay.
+    pub fn release(self) -> (I2C, D) {
+        (self.i2c, self.delay)
+    }
+
+    /// Current measurement state.
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Get the 48-bit serial ID.
+    pub fn serial_id(&mut self) -> Result<[u8; 6], Error> {
+        let [a, b, c] = self.read_words::<3>(&Self::GET_SERIAL_ID, 1)?;
+        let ([a0, a1], [b0, b1], [c0, c1]) = (a.to_be_bytes(), b.to_be_bytes(), c.to_be_bytes());
+
+        Ok([a0, a1, b0, b1, c0, c1])
+    }
+
+    /// Get product type and version.
+    pub fn feature_set(&mut self) -> Result<FeatureSet, Error> {
+        let [value] = self.read_words::<1>(&Self::GET_FEATURE_SET, 10)?;
+
+        Ok(FeatureSet(value))
+    }
+
+    /// Start the air quality measurement.
+    ///
+    /// After this, [`Sgp30::measure_iaq`] must be called at 1 second intervals
+    /// for the dynamic baseline compensation algorithm.
+    pub fn iaq_init(&mut self) -> Result<(), Error> {
+        self.write_words(&Self::IAQ_INIT, &[], 10)?;
+        self.state = State::Initializing { samples: 0 };
+
+        Ok(())
+    }
+
+    /// Measure air quality.
+    ///
+    /// Returns [`Error::NotReady`] until the initialization phase has completed.
+    pub fn measure_iaq(&mut self) -> Result<IaqMeasurement, Error> {
+        if self.state == State::Uninitialized {
+            return Err(Error::NotReady);
+        }
+
+        let [co2_eq, tvoc] = self.read_words::<2>(&Self::MEASURE_IAQ, 12)?;
+
+        if let State::Initializing { samples } = self.state {
+            let samples = samples + 1;
+            self.state = if samples > Self::INIT_SAMPLES {
+                State::Measuring
+            } else {
+                State::Initializing { samples }
+            };
+
+            return Err(Error::NotReady);
+        }
+
+        Ok(IaqMeasurement { co2_eq, tvoc })
+    }
+
+    /// Get the current IAQ algorithm baseline.
+    pub fn iaq_baseline(&mut self) -> Result<Baseline, Error> {
+        let [co2_eq, tvoc] = self.read_words::<2>(&Self::GET_IAQ_BASELINE, 10)?;
+
+        Ok(Baseline { co2_eq, tvoc })
+    }
+
+    /// Restore a previously saved IAQ algorithm baseline.
+    ///
+    /// Must be called after [`Sgp30::iaq_init`].
+    pub fn set_iaq_baseline(&mut self, baseline: Baseline) -> Result<(), Error> {
+        // Baseline is written in reverse order to how it is read
+        self.write_words(&Self::SET_IAQ_BASELINE, &[baseline.tvoc, baseline.co2_eq], 10)
+    }
+
+    /// Set absolute humidity (g/m³) in 8.8 fixed-point format for humidity compensation.
+    ///
+    /// A value of 0 disables humidity compensation.
+    pub fn set_abs_humidity(&mut self, value: u16) -> Result<(), Error> {
+        self.write_words(&Self::SET_ABS_HUMIDITY, &[value], 10)
+    }
+
+    /// Set humidity compensation from ambient temperature (degC) and relative humidity (%RH).
+    ///
+    /// Returns the absolute humidity (g/m³) that was set.
+    pub fn set_humidity_compensation(&mut self, temperature: f32, relative_humidity: f32) -> Result<f32, Error> {
+        let value = absolute_humidity(temperature, relative_humidity);
+        self.set_abs_humidity(abs_humidity_to_fixed(value))?;
+
+        Ok(value)
+    }
+
+    /// Run the on-chip self-test.
+    ///
+    /// Returns [`SELF_TEST_OK`] if all tests passed.
+    /// This interrupts any air quality measurement, so [`Sgp30::iaq_init`] must be called afterwards.
+    pub fn measure_test(&mut self) -> Result<u16, Error> {
+        self.state = State::Uninitialized;
+        let [value] = self.read_words::<1>(&Self::MEASURE_TEST, 220)?;
+
+        Ok(value)
+    }
+
+    /// Measure raw H₂ and ethanol signals.
+    pub fn measure_raw(&mut self) -> Result<RawSignals, Error> {
+        let [h2, ethanol] = self.read_words::<2>(&Self::MEASURE_RAW, 25)?;
+
+        Ok(RawSignals { h2, ethanol })
+    }
+
+    /// Get the inceptive TVOC baseline provided by the sensor.
+    pub fn tvoc_inceptive_baseline(&mut self) -> Result<u16, Error> {
+        let [value] = self.read_words::<1>(&Self::GET_TVOC_INCEPTIVE_BASELINE, 10)?;
+
+        Ok(value)
+    }
+
+    /// Set the TVOC baseline.
+    ///
+    /// Must be called after [`Sgp30::iaq_init`].
+    pub fn set_tvoc_baseline(&mut self, value: u16) -> Result<(), Error> {
+        self.write_words(&Self::SET_TVOC_BASELINE, &[value], 10)
+    }
+
+    /// Send command followed by up to 2 words and wait `delay_ms` for command to complete.
+    fn write_words(&mut self, cmd: &[u8; 2], words: &[u16], delay_ms: u32) -> Result<(), Error> {
+        let mut buf = [0u8; 2 + 3 * 2];
+        buf[..2].copy_from_slice(cmd);
+        for (n, word) in words.iter().enumerate() {
+            let data = word.to_be_bytes();
+            let offset = 2 + 3 * n;
+
+            buf[offset..offset + 2].copy_from_slice(&data);
+            buf[offset + 2] = sensirion_crc8(&data);
+        }
+
+        self.i2c.write(self.address, &buf[..2 + 3 * words.len()]).map_err(Error::i2c)?;
+        self.delay.delay_ms(delay_ms);
+
+        Ok(())
+    }
+
+    /// Send command, wait `delay_ms` for it to complete and then read `N` words (max 3).
+    fn read_words<const N: usize>(&mut self, cmd: &[u8; 2], delay_ms: u32) -> Result<[u16; N], Error> {
+        self.i2c.write(self.address, cmd).map_err(Error::i2c)?;
+        self.delay.delay_ms(delay_ms);
+
+        let mut buf = [0u8; 3 * 3];
+        let buf = &mut buf[..3 * N];
+        self.i2c.read(self.address, buf).map_err(Error::i2c)?;
+
+        let mut words = [0u16; N];
+        for (word, chunk) in words.iter_mut().zip(buf.as_chunks::<3>().0) {
+            if sensirion_crc8(&chunk[..2]) != chunk[2] {
+                return Err(Error::Crc);
+            }
+
+            *word = u16::from_be_bytes([chunk[0], chunk[1]]);
+        }
+
+        Ok(words)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use embedded_hal_mock::eh1::delay::{CheckedDelay, NoopDelay, Transaction as DelayTransaction};
+    use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction};
+    use std::vec;
+
+    use super::*;
+
+    #[test]
+    fn test_abs_humidity_to_fixed() {
+        assert_eq!(abs_humidity_to_fixed(11.5), 0x0B80);
+        assert_eq!(abs_humidity_to_fixed(0.0), 1);
+        assert_eq!(abs_humidity_to_fixed(1000.0), 0xFFFF);
+    }
+
+    #[test]
+    fn test_set_abs_humidity() {
+        let expectations = [Transaction::write(0x58, vec![0x20, 0x61, 0xBE, 0xEF, 0x92])];
+        let delays = [DelayTransaction::delay_ms(10)];
+        let mut sensor = Sgp30::with_default_addr(I2cMock::new(&expectations), CheckedDelay::new(&delays));
+
+        assert_eq!(sensor.set_abs_humidity(0xBEEF), Ok(()));
+
+        let (mut i2c, mut delay) = sensor.release();
+        i2c.done();
+        delay.done();
+    }
+
+    #[test]
+    fn test_measure_iaq() {
+        let mut expectations = vec![Transaction::write(0x58, vec![0x20, 0x03])];
+        for _ in 0..=Sgp30::<I2cMock, NoopDelay>::INIT_SAMPLES + 1 {
+            expectations.push(Transaction::write(0x58, vec![0x20, 0x08]));
+            expectations.push(Transaction::read(0x58, vec![0xBE, 0xEF, 0x92, 0xBE, 0xEF, 0x92]));
+        }
+        expectations.push(Transaction::write(0x58, vec![0x20, 0x08]));
+        expectations.push(Transaction::read(0x58, vec![0xBE, 0xEF, 0x92, 0xBE, 0xEF, 0x00]));
+
+        let mut sensor = Sgp30::with_default_addr(I2cMock::new(&expectations), NoopDelay);
+        assert_eq!(sensor.measure_iaq(), Err(Error::NotReady));
+
+        assert_eq!(sensor.iaq_init(), Ok(()));
+        assert_eq!(sensor.state(), State::Initializing { samples: 0 });
+        for _ in 0..=Sgp30::<I2cMock, NoopDelay>::INIT_SAMPLES {
+            assert_eq!(sensor.measure_iaq(), Err(Error::NotReady));
+        }
+        assert_eq!(sensor.state(), State::Measuring);
+        assert_eq!(sensor.measure_iaq(), Ok(IaqMeasurement { co2_eq: 0xBEEF, tvoc: 0xBEEF }));
+        assert_eq!(sensor.measure_iaq(), Err(Error::Crc));
+
+        sensor.release().0.done();
+    }
+}
@@ -1,9 +1,10 @@
 use core::ffi::CStr;
+use core::ptr::NonNull;
 
 use flipperzero_sys as sys;
 
 use crate::furi::pubsub::RawPubSub;
-use crate::furi::record::RawRecord;
+use crate::furi::record::{Record, RawRecord};
 
 pub type Storage = sys::Storage;
 
@@ -18,3 +19,66 @@ unsafe impl RawPubSub for Storage {
         unsafe { sys::storage_get_pubsub(this) }
     }
 }
+
+impl Record<Storage> {
+    /// Create a directory.
+    ///
+    /// Returns `true` if the directory was created or already exists.
+    pub fn simply_mkdir(&self, path: &CStr) -> bool {
+        unsafe { sys::storage_simply_mkdir(self.as_ptr(), path.as_ptr()) }
+    }
+
+    /// Open a file.
+    ///
+    /// Returns `None` if the file could not be opened.
+    pub fn open_file(&self, path: &CStr, access_mode: sys::FS_AccessMode, open_mode: sys::FS_OpenMode) -> Option<File<'_>> {
+        let file = File::new(self);
+
+        unsafe { sys::storage_file_open(file.as_ptr(), path.as_ptr(), access_mode, open_mode) }
+            .then_some(file)
+    }
+}
+
+/// Handle to an open file.
+///
+/// The file is closed when this type is dropped.
+pub struct File<'a> {
+    _storage: &'a Record<Storage>,
+    raw: NonNull<sys::File>,
+}
+
+impl<'a> File<'a> {
+    fn new(storage: &'a Record<Storage>) -> Self {
+        File {
+            _storage: storage,
+            // SAFETY: `storage_file_alloc` never returns NULL (will `furi_check` on error).
+            raw: unsafe { NonNull::new_unchecked(sys::storage_file_alloc(storage.as_ptr())) },
+        }
+    }
+
+    /// Get pointer to raw [`sys::File`].
+    pub fn as_ptr(&self) -> *mut sys::File {
+        self.raw.as_ptr()
+    }
+
+    /// Read bytes into `buf`.
+    ///
+    /// Returns the number of bytes actually read.
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        unsafe { sys::storage_file_read(self.as_ptr(), buf.as_mut_ptr().cast(), buf.len()) }
+    }
+
+    /// Write bytes from `data`.
+    ///
+    /// Returns the number of bytes actually written.
+    pub fn write(&mut self, data: &[u8]) -> usize {
+        unsafe { sys::storage_file_write(self.as_ptr(), data.as_ptr().cast(), data.len()) }
+    }
+}
+
+impl Drop for File<'_> {
+    fn drop(&mut self) {
+        // SAFETY: `storage_file_free` also closes the file if it is open.
+        unsafe { sys::storage_file_free(self.as_ptr()) }
+    }
+}
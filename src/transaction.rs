@@ -0,0 +1,367 @@
+//! Request/response transactions over a serial port.
+//!
+//! Many UART sensors only send a frame in response to a command.
+//! [`Client`] sends a command frame and waits for the matching response,
+//! decoded by a protocol specific [`FrameDecoder`].
+
+use core::fmt;
+
+use embedded_io::{ErrorKind, Read, ReadReady, Write};
+use flipperzero_sys as sys;
+
+/// Incremental decoder for response frames in a byte stream.
+pub trait FrameDecoder {
+    type Frame;
+    type Error;
+
+    /// Push a single received byte.
+    ///
+    /// Returns the decoded frame once a complete frame has been received,
+    /// or an error if the frame was corrupted (e.g. a checksum mismatch).
+    fn push(&mut self, byte: u8) -> Option<Result<Self::Frame, Self::Error>>;
+
+    /// Discard any partially received frame.
+    fn reset(&mut self);
+}
+
+/// Transaction errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error<F, E> {
+    /// No response was received before the port or the response timeout elapsed.
+    Timeout,
+    /// Reading or writing failed (e.g. a UART framing error).
+    Line(E),
+    /// Every attempt received a corrupted response; contains the last decoder error.
+    Corrupted(F),
+}
+
+impl<F: fmt::Display, E: fmt::Debug> fmt::Display for Error<F, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Timeout => f.write_str("timed out"),
+            Error::Line(err) => write!(f, "line error: {err:?}"),
+            Error::Corrupted(err) => write!(f, "corrupted response: {err}"),
+        }
+    }
+}
+
+/// Request/response client.
+///
+/// How long to wait for each byte of a response is set by the port (e.g. [`SerialReader::set_timeout_ms`]).
+/// The whole response must also arrive within [`Self::set_response_timeout_ms`], so that unrelated
+/// frames or noise can't stall a request.
+///
+/// [`SerialReader::set_timeout_ms`]: crate::furi::hal::serial::SerialReader::set_timeout_ms
+pub struct Client<P, D> {
+    port: P,
+    decoder: D,
+    retries: u8,
+    response_timeout_ms: u64,
+    /// Current time (ms).
+    clock: fn() -> u32,
+}
+
+impl<P, D> Client<P, D>
+where
+    P: Read + ReadReady + Write,
+    D: FrameDecoder,
+{
+    /// Default number of times a request is repeated after a corrupted response.
+    pub const DEFAULT_RETRIES: u8 = 2;
+    /// Default time to wait for a complete response.
+    pub const DEFAULT_RESPONSE_TIMEOUT_MS: u64 = 1000;
+
+    pub fn new(port: P, decoder: D) -> Self {
+        Client {
+            port,
+            decoder,
+            retries: Self::DEFAULT_RETRIES,
+            response_timeout_ms: Self::DEFAULT_RESPONSE_TIMEOUT_MS,
+            clock: furi_tick_ms,
+        }
+    }
+
+    /// Set how many times a request is repeated after a corrupted response.
+    pub fn set_retries(&mut self, retries: u8) {
+        self.retries = retries;
+    }
+
+    /// Set the time to wait for a complete response to each attempt.
+    pub fn set_response_timeout_ms(&mut self, timeout_ms: u64) {
+        self.response_timeout_ms = timeout_ms;
+    }
+
+    /// Borrow the underlying port.
+    pub fn port(&mut self) -> &mut P {
+        &mut self.port
+    }
+
+    /// Release the underlying port and decoder.
+    pub fn into_inner(self) -> (P, D) {
+        (self.port, self.decoder)
+    }
+
+    /// Send a command that has no response.
+    pub fn send(&mut self, command: &[u8]) -> Result<(), Error<D::Error, P::Error>> {
+        self.port.write_all(command).map_err(Error::Line)?;
+        self.port.flush().map_err(Error::Line)
+    }
+
+    /// Send `request` and wait for the next response frame.
+    pub fn request(&mut self, request: &[u8]) -> Result<D::Frame, Error<D::Error, P::Error>> {
+        self.request_matching(request, |_| true)
+    }
+
+    /// Send `request` and wait for a response frame accepted by `is_response`.
+    ///
+    /// Other frames are ignored. The request is repeated if a corrupted frame is received.
+    pub fn request_matching(
+        &mut self,
+        request: &[u8],
+        is_response: impl Fn(&D::Frame) -> bool,
+    ) -> Result<D::Frame, Error<D::Error, P::Error>> {
+        let mut attempt = 0;
+
+        loop {
+            self.discard_stale();
+            self.send(request)?;
+
+            match self.receive(&is_response) {
+                Err(Error::Corrupted(_)) if attempt < self.retries => attempt += 1,
+                result => return result,
+            }
+        }
+    }
+
+    /// Receive until a matching frame or a corrupted frame.
+    fn receive(&mut self, is_response: &impl Fn(&D::Frame) -> bool) -> Result<D::Frame, Error<D::Error, P::Error>> {
+        let mut buf = [0u8; 32];
+        let started = (self.clock)();
+
+        loop {
+            if u64::from((self.clock)().wrapping_sub(started)) >= self.response_timeout_ms {
+                return Err(Error::Timeout);
+            }
+
+            let len = match self.port.read(&mut buf) {
+                Ok(len) => len,
+                Err(err) if embedded_io::Error::kind(&err) == ErrorKind::TimedOut => return Err(Error::Timeout),
+                Err(err) => return Err(Error::Line(err)),
+            };
+
+            for &byte in &buf[..len] {
+                match self.decoder.push(byte) {
+                    Some(Ok(frame)) if is_response(&frame) => return Ok(frame),
+                    Some(Err(err)) => return Err(Error::Corrupted(err)),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Discard late responses and errors from previous requests.
+    fn discard_stale(&mut self) {
+        let mut buf = [0u8; 32];
+        while let Ok(true) = self.port.read_ready() {
+            if let Ok(0) = self.port.read(&mut buf) {
+                break;
+            }
+        }
+
+        self.decoder.reset();
+    }
+}
+
+/// Furi tick count, which runs at 1 kHz.
+fn furi_tick_ms() -> u32 {
+    unsafe { sys::furi_get_tick() }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::cell::Cell;
+    use std::collections::VecDeque;
+    use std::vec::Vec;
+
+    use embedded_io::ErrorType;
+
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum MockError {
+        Timeout,
+        Noise,
+    }
+
+    impl embedded_io::Error for MockError {
+        fn kind(&self) -> ErrorKind {
+            match self {
+                MockError::Timeout => ErrorKind::TimedOut,
+                MockError::Noise => ErrorKind::InvalidData,
+            }
+        }
+    }
+
+    /// Port that replies to each write with the next scripted response.
+    #[derive(Default)]
+    struct MockPort {
+        /// Response (or error) for each request, in order.
+        responses: VecDeque<Result<Vec<u8>, MockError>>,
+        /// Received but unread bytes.
+        rx: VecDeque<u8>,
+        /// Error returned by the next read.
+        error: Option<MockError>,
+        written: Vec<u8>,
+    }
+
+    impl ErrorType for MockPort {
+        type Error = MockError;
+    }
+
+    impl Read for MockPort {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, MockError> {
+            if let Some(err) = self.error.take() {
+                return Err(err);
+            }
+
+            let len = buf.len().min(self.rx.len());
+            if len == 0 {
+                return Err(MockError::Timeout);
+            }
+
+            for byte in &mut buf[..len] {
+                *byte = self.rx.pop_front().unwrap();
+            }
+
+            Ok(len)
+        }
+    }
+
+    impl ReadReady for MockPort {
+        fn read_ready(&mut self) -> Result<bool, MockError> {
+            Ok(self.error.is_some() || !self.rx.is_empty())
+        }
+    }
+
+    impl Write for MockPort {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, MockError> {
+            self.written.extend_from_slice(buf);
+
+            match self.responses.pop_front() {
+                Some(Ok(response)) => self.rx.extend(response),
+                Some(Err(err)) => self.error = Some(err),
+                None => {}
+            }
+
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<(), MockError> {
+            Ok(())
+        }
+    }
+
+    /// Frames are `[0xAA, value, !value]`.
+    #[derive(Default)]
+    struct MockDecoder {
+        buffer: Vec<u8>,
+    }
+
+    impl FrameDecoder for MockDecoder {
+        type Frame = u8;
+        type Error = u8;
+
+        fn push(&mut self, byte: u8) -> Option<Result<u8, u8>> {
+            if self.buffer.is_empty() && byte != 0xAA {
+                return None;
+            }
+
+            self.buffer.push(byte);
+            if self.buffer.len() < 3 {
+                return None;
+            }
+
+            let frame = core::mem::take(&mut self.buffer);
+            match frame[2] == !frame[1] {
+                true => Some(Ok(frame[1])),
+                false => Some(Err(frame[1])),
+            }
+        }
+
+        fn reset(&mut self) {
+            self.buffer.clear();
+        }
+    }
+
+    std::thread_local! {
+        static NOW: Cell<u32> = const { Cell::new(0) };
+    }
+
+    /// Clock that advances by 10 ms on every call.
+    fn mock_clock() -> u32 {
+        NOW.with(|now| now.replace(now.get().wrapping_add(10)))
+    }
+
+    fn client(responses: impl IntoIterator<Item = Result<Vec<u8>, MockError>>) -> Client<MockPort, MockDecoder> {
+        let port = MockPort { responses: responses.into_iter().collect(), ..Default::default() };
+
+        // Not `Client::new`, which uses the Furi tick
+        Client {
+            port,
+            decoder: MockDecoder::default(),
+            retries: Client::<MockPort, MockDecoder>::DEFAULT_RETRIES,
+            response_timeout_ms: Client::<MockPort, MockDecoder>::DEFAULT_RESPONSE_TIMEOUT_MS,
+            clock: mock_clock,
+        }
+    }
+
+    #[test]
+    fn test_request() {
+        let mut client = client([Ok(std::vec![0x00, 0xAA, 0x01, 0xFE, 0xAA, 0x02, 0xFD])]);
+
+        assert_eq!(client.request_matching(b"?", |&value| value == 2), Ok(2));
+        assert_eq!(client.request(b"?"), Err(Error::Timeout));
+        assert_eq!(client.port().written, b"??");
+    }
+
+    #[test]
+    fn test_request_retries() {
+        let mut client = client([
+            Ok(std::vec![0xAA, 0x01, 0x00]),
+            Ok(std::vec![0xAA, 0x02, 0x00]),
+            Ok(std::vec![0xAA, 0x03, 0xFC]),
+            Ok(std::vec![0xAA, 0x04, 0x00]),
+            Ok(std::vec![0xAA, 0x05, 0x00]),
+        ]);
+
+        assert_eq!(client.request(b"?"), Ok(3));
+
+        client.set_retries(1);
+        assert_eq!(client.request(b"?"), Err(Error::Corrupted(0x05)));
+        assert_eq!(client.port().written.len(), 5);
+    }
+
+    #[test]
+    fn test_request_discards_stale() {
+        let mut client = client([Err(MockError::Noise), Ok(std::vec![0xAA, 0x02, 0xFD])]);
+
+        assert_eq!(client.request(b"?"), Err(Error::Line(MockError::Noise)));
+
+        // Late response to the previous request
+        client.port().rx.extend([0xAA, 0x01, 0xFE]);
+        assert_eq!(client.request(b"?"), Ok(2));
+    }
+
+    #[test]
+    fn test_request_response_timeout() {
+        // Unrelated frames keep arriving, so the port never times out
+        let frames = [0xAA, 0x01, 0xFE].repeat(100);
+        let mut client = client([Ok(frames)]);
+        client.set_response_timeout_ms(50);
+
+        assert_eq!(client.request_matching(b"?", |&value| value == 2), Err(Error::Timeout));
+        assert!(!client.port().rx.is_empty());
+    }
+}
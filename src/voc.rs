@@ -0,0 +1,24 @@
+//! VOC pollution grades of Winsen ZP01-MP503 based modules.
+
+/// VOC levels reported by ZP01-MP503.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VOCLevel {
+    Clean,
+    Light,
+    Moderate,
+    Severe,
+}
+
+impl TryFrom<u8> for VOCLevel {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(VOCLevel::Clean),
+            1 => Ok(VOCLevel::Light),
+            2 => Ok(VOCLevel::Moderate),
+            3 => Ok(VOCLevel::Severe),
+            _ => Err(value),
+        }
+    }
+}
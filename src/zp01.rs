@@ -0,0 +1,115 @@
+//! Winsen ZP01-MP503 air quality module.
+//!
+//! The module reports the VOC pollution grade on two 5V TTL outputs (A and B).
+//! The Flipper's GPIO pins are 5V tolerant, so these can be connected directly.
+//! See `datasheets/zp01-mp503.pdf`.
+
+use flipperzero_sys as sys;
+
+use crate::furi::hal::gpio::{Input, Pin, Pull};
+use crate::voc::VOCLevel;
+
+/// ZP01-MP503 connected to two GPIO pins.
+pub struct Zp01 {
+    a: Input,
+    b: Input,
+    /// RTC timestamp at which the module was powered on.
+    powered_on: u32,
+}
+
+impl Zp01 {
+    /// Time the module needs to warm up before its outputs are valid.
+    pub const PREHEAT_SECS: u32 = 3 * 60;
+
+    /// Module with output A on pin `a` and output B on pin `b`.
+    ///
+    /// The module is assumed to have been powered on just now.
+    /// Inputs are pulled down, so a disconnected module reads as clean.
+    ///
+    /// # Safety
+    ///
+    /// `a` and `b` must be exclusive to the module, see [`Input::new`].
+    pub unsafe fn new(a: Pin, b: Pin) -> Self {
+        Zp01 {
+            a: unsafe { Input::new(a, Pull::Down) },
+            b: unsafe { Input::new(b, Pull::Down) },
+            powered_on: rtc_timestamp(),
+        }
+    }
+
+    /// Module that was powered on at RTC timestamp `powered_on`.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Self::new`].
+    pub unsafe fn with_power_on_time(a: Pin, b: Pin, powered_on: u32) -> Self {
+        Zp01 {
+            powered_on,
+            ..unsafe { Self::new(a, b) }
+        }
+    }
+
+    /// Seconds until the module has warmed up.
+    pub fn preheat_remaining(&self) -> u32 {
+        let elapsed = rtc_timestamp().saturating_sub(self.powered_on);
+
+        Self::PREHEAT_SECS.saturating_sub(elapsed)
+    }
+
+    /// Module has warmed up.
+    pub fn is_preheated(&self) -> bool {
+        self.preheat_remaining() == 0
+    }
+
+    /// Current VOC level.
+    ///
+    /// Returns `None` while the module is warming up.
+    pub fn level(&self) -> Option<VOCLevel> {
+        self.is_preheated().then(|| self.read_level())
+    }
+
+    /// Current VOC level, even if the module has not warmed up.
+    pub fn read_level(&self) -> VOCLevel {
+        Self::decode(self.a.is_high(), self.b.is_high())
+    }
+
+    /// Decode the level of outputs A and B.
+    ///
+    /// | A  | B  | Grade    |
+    /// |----|----|----------|
+    /// | 0V | 0V | Clean    |
+    /// | 0V | 5V | Light    |
+    /// | 5V | 0V | Moderate |
+    /// | 5V | 5V | Severe   |
+    pub const fn decode(a: bool, b: bool) -> VOCLevel {
+        match (a, b) {
+            (false, false) => VOCLevel::Clean,
+            (false, true) => VOCLevel::Light,
+            (true, false) => VOCLevel::Moderate,
+            (true, true) => VOCLevel::Severe,
+        }
+    }
+}
+
+/// Current RTC time (seconds since UNIX epoch).
+fn rtc_timestamp() -> u32 {
+    unsafe { sys::furi_hal_rtc_get_timestamp() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(Zp01::decode(false, false), VOCLevel::Clean);
+        assert_eq!(Zp01::decode(false, true), VOCLevel::Light);
+        assert_eq!(Zp01::decode(true, false), VOCLevel::Moderate);
+        assert_eq!(Zp01::decode(true, true), VOCLevel::Severe);
+
+        for grade in 0..=3u8 {
+            let level = Zp01::decode(grade & 0b10 != 0, grade & 0b01 != 0);
+            assert_eq!(VOCLevel::try_from(grade), Ok(level));
+        }
+    }
+}
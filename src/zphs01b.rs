@@ -0,0 +1,420 @@
+//! Protocol for the
+//! [Winsen ZPHS01B Multi-in-One Sensor Module](https://www.winsen-sensor.com/product/zphs01b.html).
+//!
+//! The module uses a question and answer protocol over UART (9600 baud, 8N1).
+
+use core::fmt;
+use core::ops::Not;
+
+use crate::transaction::FrameDecoder;
+use crate::voc::VOCLevel;
+
+/// First byte of every command and response frame.
+pub const START_BYTE: u8 = 0xFF;
+
+/// Size of a command frame.
+pub const COMMAND_SIZE: usize = 9;
+
+/// Size of a measurement response frame.
+pub const RESPONSE_SIZE: usize = 26;
+
+/// Command to read the module concentration values.
+///
+/// This is the only command documented by the v1.3 manual (`datasheets/zphs01b-*.pdf`).
+pub const CMD_FETCH: [u8; COMMAND_SIZE] = [0xFF, 0x01, 0x86, 0x00, 0x00, 0x00, 0x00, 0x00, 0x79];
+
+/// Command byte of a measurement response.
+const FETCH: u8 = 0x86;
+
+/// Calculate frame checksum.
+///
+/// Checksum is the two's complement of the sum of all bytes excluding the start byte and checksum.
+pub fn calculate_checksum(data: &[u8]) -> u8 {
+    data.iter()
+        .fold(0u8, |acc, &x| acc.wrapping_add(x))
+        .not()
+        .wrapping_add(1)
+}
+
+/// Frame decoding errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// Frame is not [`RESPONSE_SIZE`] bytes long.
+    InvalidLength(usize),
+    /// Frame does not begin with [`START_BYTE`].
+    InvalidStartByte(u8),
+    /// Frame is not a response to [`CMD_FETCH`].
+    UnexpectedCommand(u8),
+    /// Checksum of frame does not match its contents.
+    Checksum { expected: u8, actual: u8 },
+    /// VOC grade is outside the range 0 to 3.
+    InvalidVocLevel(u8),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidLength(len) => write!(f, "invalid frame length: {len}"),
+            Error::InvalidStartByte(byte) => write!(f, "invalid start byte: 0x{byte:02X}"),
+            Error::UnexpectedCommand(cmd) => write!(f, "unexpected command: 0x{cmd:02X}"),
+            Error::Checksum { expected, actual } => {
+                write!(f, "bad checksum: 0x{actual:02X} != 0x{expected:02X}")
+            }
+            Error::InvalidVocLevel(voc) => write!(f, "invalid VOC level: {voc}"),
+        }
+    }
+}
+
+/// Measurement values reported by the module.
+///
+/// Fields contain the raw fixed-point values from the response frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Measurement {
+    pub pm_1: u16,
+    pub pm_2_5: u16,
+    pub pm_10: u16,
+    pub co2: u16,
+    pub voc: u8,
+    pub temp: u16,
+    pub relative_humidity: u16,
+    pub ch2o: u16,
+    pub co: u16,
+    pub o3: u16,
+    pub no2: u16,
+}
+
+impl Measurement {
+    pub const fn new() -> Self {
+        Measurement {
+            pm_1: 0,
+            pm_2_5: 0,
+            pm_10: 0,
+            co2: 0,
+            voc: 0,
+            temp: 0,
+            relative_humidity: 0,
+            ch2o: 0,
+            co: 0,
+            o3: 0,
+            no2: 0,
+        }
+    }
+
+    /// Overall air quality.
+    /// Based on the EPA AQI (8 hours)
+    pub fn air_quality_index(&self) -> AirQualityIndex {
+        let o3 = self.o3();
+        let (_pm_1, pm_2_5, pm_10) = self.pm();
+        let co = self.co();
+        let no2 = self.no2();
+
+        // Hazardous (Maroon)
+        if o3 > 0.200 || pm_2_5 > 250 || pm_10 > 424 || co > 30.0 || no2 > 1.249 {
+            return AirQualityIndex::Hazardous;
+        }
+
+        // Very Unhealthy (Purple)
+        if o3 > 0.105 || pm_2_5 > 150 || pm_10 > 354 || co > 15.4 || no2 > 0.649 {
+            return AirQualityIndex::VeryUnhealthy;
+        }
+
+        // Unhealthy (Red)
+        if o3 > 0.085 || pm_2_5 > 55 || pm_10 > 254 || co > 12.4 || no2 > 0.360 {
+            return AirQualityIndex::Unhealthy;
+        }
+
+        // Unhealthy for Sensitive Groups (Orange)
+        if o3 > 0.070 || pm_2_5 > 35 || pm_10 > 154 || co > 9.4 || no2 > 0.100 {
+            return AirQualityIndex::Sensitive;
+        }
+
+        // Moderate (Yellow)
+        if o3 > 0.054 || pm_2_5 > 12 || pm_10 > 54 || co > 4.4 || no2 > 0.053 {
+            return AirQualityIndex::Moderate;
+        }
+
+        // Good (Green)
+        AirQualityIndex::Good
+    }
+
+    /// Particulate matter (μg/m) for PM 1, PM 2.5 and PM 10.
+    pub fn pm(&self) -> (u16, u16, u16) {
+        (self.pm_1, self.pm_2_5, self.pm_10)
+    }
+
+    /// Carbon Dioxide (ppm CO₂).
+    /// Ideally should be below 1000 ppm.
+    pub fn co2(&self) -> u16 {
+        self.co2
+    }
+
+    /// Volitile Organic Compounds.
+    pub fn voc(&self) -> VOCLevel {
+        VOCLevel::try_from(self.voc).unwrap_or(VOCLevel::Severe)
+    }
+
+    /// Temperature (°C) accurate to 1 decimal place.
+    pub fn temperature(&self) -> f64 {
+        0.10 * self.temp as f64 - 50.0
+    }
+
+    /// Relative Humidity (%).
+    pub fn relative_humidity(&self) -> u16 {
+        self.relative_humidity
+    }
+
+    /// Formaldehyde (mg/m³ CH₂O) accurate to 3 decimal places.
+    /// Ideally should be below 0.1 mg/m³.
+    pub fn ch2o(&self) -> f64 {
+        0.001 * self.ch2o as f64
+    }
+
+    /// Carbon Monoxide (ppm CO) accurate to 1 decimal place.
+    /// Ideally should be below 9 ppm.
+    pub fn co(&self) -> f64 {
+        0.1 * self.co as f64
+    }
+
+    /// Ozone (ppm O₃) accurate to 2 decimal places.
+    /// Ideally should be below 0.08 ppm.
+    pub fn o3(&self) -> f64 {
+        0.01 * self.o3 as f64
+    }
+
+    /// Nitrogen Dioxide (ppm NO₂) accurate to 2 decimal places.
+    pub fn no2(&self) -> f64 {
+        0.01 * self.no2 as f64
+    }
+}
+
+impl TryFrom<&[u8]> for Measurement {
+    type Error = Error;
+
+    fn try_from(buffer: &[u8]) -> Result<Self, Self::Error> {
+        if buffer.len() != RESPONSE_SIZE {
+            return Err(Error::InvalidLength(buffer.len()));
+        }
+
+        if buffer[0] != START_BYTE {
+            return Err(Error::InvalidStartByte(buffer[0]));
+        }
+
+        let expected = calculate_checksum(&buffer[1..25]);
+        if expected != buffer[25] {
+            return Err(Error::Checksum { expected, actual: buffer[25] });
+        }
+
+        if buffer[1] != FETCH {
+            return Err(Error::UnexpectedCommand(buffer[1]));
+        }
+
+        if VOCLevel::try_from(buffer[10]).is_err() {
+            return Err(Error::InvalidVocLevel(buffer[10]));
+        }
+
+        Ok(Measurement {
+            pm_1: read_u16_be(&buffer[2..4]),
+            pm_2_5: read_u16_be(&buffer[4..6]),
+            pm_10: read_u16_be(&buffer[6..8]),
+            co2: read_u16_be(&buffer[8..10]),
+            voc: buffer[10],
+            temp: read_u16_be(&buffer[11..13]),
+            relative_humidity: read_u16_be(&buffer[13..15]),
+            ch2o: read_u16_be(&buffer[15..17]),
+            co: read_u16_be(&buffer[17..19]),
+            o3: read_u16_be(&buffer[19..21]),
+            no2: read_u16_be(&buffer[21..23]),
+        })
+    }
+}
+
+/// Incremental decoder for measurement frames in a byte stream.
+///
+/// Bytes before a [`START_BYTE`] are discarded, so the decoder resynchronizes
+/// after garbage or a corrupted frame regardless of how the stream is chunked.
+#[derive(Debug, Default)]
+pub struct Decoder {
+    buffer: heapless::Vec<u8, RESPONSE_SIZE>,
+}
+
+impl Decoder {
+    pub const fn new() -> Self {
+        Decoder { buffer: heapless::Vec::new() }
+    }
+
+    /// Discard any partially received frame.
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+    }
+
+    /// Decode all frames completed by `data`.
+    pub fn decode<'a>(&'a mut self, data: &'a [u8]) -> impl Iterator<Item = Result<Measurement, Error>> + 'a {
+        data.iter().filter_map(move |&byte| self.push(byte))
+    }
+
+    /// Push a single byte into the decoder.
+    ///
+    /// Returns the decoded frame once a complete frame has been received.
+    pub fn push(&mut self, byte: u8) -> Option<Result<Measurement, Error>> {
+        if self.buffer.is_empty() && byte != START_BYTE {
+            return None;
+        }
+
+        // Buffer is never full here, since it's emptied once it reaches `RESPONSE_SIZE`
+        self.buffer.push(byte).ok();
+
+        if self.buffer.len() < RESPONSE_SIZE {
+            return None;
+        }
+
+        let result = Measurement::try_from(&self.buffer[..]);
+        if result.is_ok() {
+            self.buffer.clear();
+        } else {
+            self.resync();
+        }
+
+        Some(result)
+    }
+
+    /// Drop bytes up to the next start byte after the current frame start.
+    fn resync(&mut self) {
+        match self.buffer[1..].iter().position(|&b| b == START_BYTE) {
+            None => self.buffer.clear(),
+            Some(pos) => {
+                let start = pos + 1;
+                let len = self.buffer.len() - start;
+
+                self.buffer.copy_within(start.., 0);
+                self.buffer.truncate(len);
+            }
+        }
+    }
+}
+
+impl FrameDecoder for Decoder {
+    type Frame = Measurement;
+    type Error = Error;
+
+    fn push(&mut self, byte: u8) -> Option<Result<Measurement, Error>> {
+        Decoder::push(self, byte)
+    }
+
+    fn reset(&mut self) {
+        Decoder::reset(self)
+    }
+}
+
+/// Air Quality Index (see https://www.airnow.gov/aqi/aqi-basics/)
+/// 0 to 50: Good (Green)
+/// 51 to 100: Moderate (Yellow)
+/// 101 to 150: Unhealthy for Sensitive Groups (Orange)
+/// 151 to 200: Unhealthy (Red)
+/// 201 to 300: Very Unhealthy (Purple)
+/// 301 to 500: Hazardous (Maroon)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AirQualityIndex {
+    Good,
+    Moderate,
+    Sensitive,
+    Unhealthy,
+    VeryUnhealthy,
+    Hazardous,
+}
+
+fn read_u16_be(data: &[u8]) -> u16 {
+    (data[0] as u16) << 8 | data[1] as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test data
+    /// PM1.0 = 101 ug/m3
+    /// PM2.5 = 54 ug/m3
+    /// PM10 = 150 ug/m3
+    /// CO2 = 410 ppm
+    /// VOC = 0
+    /// Temp = 26.5 degC
+    /// Humidity = 40% RH
+    /// CH2O = 0.040 mg/m3
+    /// CO = 0.5 ppm
+    /// O3 = 0.32 ppm
+    /// NO2 = 0.80 ppm
+    const TEST_DATA: [u8; RESPONSE_SIZE] = [0xFF, 0x86, 0x00, 0x65, 0x00, 0x36, 0x00, 0x96, 0x01, 0x9A, 0x00, 0x02, 0xFD, 0x00, 0x28, 0x00, 0x28, 0x00, 0x05, 0x00, 0x20, 0x00, 0x50, 0x00, 0x00, 0xEA];
+
+    const TEST_MEASUREMENT: Measurement = Measurement {
+        pm_1: 101,
+        pm_2_5: 54,
+        pm_10: 150,
+        co2: 410,
+        voc: 0,
+        temp: 765,
+        relative_humidity: 40,
+        ch2o: 40,
+        co: 5,
+        o3: 32,
+        no2: 80,
+    };
+
+    #[test]
+    fn test_checksum() {
+        assert_eq!(calculate_checksum(&CMD_FETCH[1..8]), CMD_FETCH[8]);
+        assert_eq!(calculate_checksum(&TEST_DATA[1..25]), TEST_DATA[25]);
+    }
+
+    #[test]
+    fn test_measurement_decode() {
+        let measurement = Measurement::try_from(&TEST_DATA[..]).unwrap();
+        assert_eq!(measurement, TEST_MEASUREMENT);
+        assert_eq!(measurement.voc(), VOCLevel::Clean);
+        assert!((measurement.temperature() - 26.5).abs() < 1e-9);
+
+        assert_eq!(Measurement::try_from(&TEST_DATA[..25]), Err(Error::InvalidLength(25)));
+
+        let mut bad = TEST_DATA;
+        bad[3] ^= 1;
+        assert!(matches!(Measurement::try_from(&bad[..]), Err(Error::Checksum { .. })));
+    }
+
+    #[test]
+    fn test_decoder_split_frames() {
+        let mut decoder = Decoder::new();
+
+        for chunk in TEST_DATA.chunks(5) {
+            let results: heapless::Vec<_, 4> = decoder.decode(chunk).collect();
+            if chunk.as_ptr_range().end == TEST_DATA.as_ptr_range().end {
+                assert_eq!(&results[..], &[Ok(TEST_MEASUREMENT)]);
+            } else {
+                assert!(results.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_decoder_concatenated_frames() {
+        let mut data: heapless::Vec<u8, 64> = heapless::Vec::new();
+        data.extend_from_slice(&[0x12, 0x34]).unwrap();
+        data.extend_from_slice(&TEST_DATA).unwrap();
+        data.extend_from_slice(&TEST_DATA).unwrap();
+
+        let mut decoder = Decoder::new();
+        let results: heapless::Vec<_, 4> = decoder.decode(&data).collect();
+        assert_eq!(&results[..], &[Ok(TEST_MEASUREMENT), Ok(TEST_MEASUREMENT)]);
+    }
+
+    #[test]
+    fn test_decoder_resync() {
+        // Truncated frame followed by a complete frame
+        let mut data: heapless::Vec<u8, 64> = heapless::Vec::new();
+        data.extend_from_slice(&TEST_DATA[..10]).unwrap();
+        data.extend_from_slice(&TEST_DATA).unwrap();
+
+        let mut decoder = Decoder::new();
+        let results: heapless::Vec<_, 4> = decoder.decode(&data).collect();
+        assert_eq!(results.len(), 2);
+        assert!(matches!(results[0], Err(Error::Checksum { .. })));
+        assert_eq!(results[1], Ok(TEST_MEASUREMENT));
+    }
+}